@@ -0,0 +1,5 @@
+-- Your SQL goes here
+CREATE TABLE guild_config(
+    guild_id BIGINT NOT NULL PRIMARY KEY,
+    prefix TEXT
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE feature_toggle(
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    feature TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, channel_id, feature)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM history_window_config WHERE window_size IS NULL;
+ALTER TABLE history_window_config DROP COLUMN max_age;
+ALTER TABLE history_window_config ALTER COLUMN window_size SET NOT NULL;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE history_window_config ALTER COLUMN window_size DROP NOT NULL;
+ALTER TABLE history_window_config ADD COLUMN max_age BIGINT;
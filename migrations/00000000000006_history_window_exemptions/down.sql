@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE history
+    DROP COLUMN author_id,
+    DROP COLUMN author_roles,
+    DROP COLUMN has_attachments,
+    DROP COLUMN pinned;
+
+ALTER TABLE history_window_config
+    DROP COLUMN exempt_pinned,
+    DROP COLUMN exempt_attachments,
+    DROP COLUMN exempt_users,
+    DROP COLUMN exempt_roles;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE history_window_config
+    ADD COLUMN exempt_pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN exempt_attachments BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN exempt_users BIGINT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN exempt_roles BIGINT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE history
+    ADD COLUMN author_id BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN author_roles BIGINT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN has_attachments BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
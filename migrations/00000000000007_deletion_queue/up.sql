@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE deletion_queue (
+    message_id BIGINT PRIMARY KEY,
+    channel_id BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt BIGINT NOT NULL,
+    last_error TEXT,
+    dead BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX deletion_queue_next_attempt ON deletion_queue (next_attempt) WHERE NOT dead;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE message_archive;
+
+ALTER TABLE history_window_config DROP COLUMN archive;
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+ALTER TABLE history_window_config ADD COLUMN archive BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE message_archive (
+    message_id BIGINT PRIMARY KEY,
+    channel_id BIGINT NOT NULL,
+    author_id BIGINT NOT NULL,
+    author_name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    attachments JSONB NOT NULL,
+    embeds JSONB NOT NULL,
+    -- Messages in archiving channels are copied when they are recorded; the
+    -- copy only joins the archive once the window deletes the message.
+    trimmed BOOLEAN NOT NULL
+);
+
+CREATE INDEX message_archive_channel ON message_archive (channel_id, message_id);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE deletion_queue DROP COLUMN archive;
+
+DELETE FROM history_window_config WHERE channel_id = 0 OR category;
+ALTER TABLE history_window_config DROP CONSTRAINT history_window_config_pkey;
+ALTER TABLE history_window_config ADD PRIMARY KEY (channel_id);
+ALTER TABLE history_window_config
+    DROP COLUMN guild_id,
+    DROP COLUMN category;
+
+DELETE FROM history WHERE channel_id NOT IN (SELECT channel_id FROM history_window_config);
+ALTER TABLE history DROP COLUMN guild_id;
+ALTER TABLE history
+    ADD FOREIGN KEY (channel_id) REFERENCES history_window_config ON DELETE CASCADE;
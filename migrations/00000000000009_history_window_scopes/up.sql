@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE history DROP CONSTRAINT history_channel_id_fkey;
+ALTER TABLE history ADD COLUMN guild_id BIGINT NOT NULL DEFAULT 0;
+
+-- Channel id 0 holds the guild default; category rows apply to the channels
+-- under that category. Rows created before guild ids were stored keep 0 until
+-- they are next configured.
+ALTER TABLE history_window_config
+    ADD COLUMN guild_id BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN category BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE history_window_config DROP CONSTRAINT history_window_config_pkey;
+ALTER TABLE history_window_config ADD PRIMARY KEY (guild_id, channel_id);
+
+ALTER TABLE deletion_queue ADD COLUMN archive BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Threads only follow their parent channel's setting when it opts them in.
+ALTER TABLE history_window_config ADD COLUMN threads BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,227 @@
+use super::Text;
+use crate::error::{Error, Result};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    positional: Vec<String>,
+    flags: HashSet<String>,
+}
+
+// Splits on whitespace, honouring "double" and 'single' quotes and backslash
+// escapes. The flag marks tokens that were written without any quoting.
+fn tokenize(input: &str) -> Result<Vec<(String, bool)>> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    let mut quote = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| Error::UserInput(Text::new("args.trailing_escape")))?;
+                token.push(escaped);
+                in_token = true;
+            }
+            ('"', None) | ('\'', None) => {
+                quote = Some(c);
+                quoted = true;
+                in_token = true;
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if in_token {
+                    tokens.push((std::mem::take(&mut token), !quoted));
+                    in_token = false;
+                    quoted = false;
+                }
+            }
+            (c, _) => {
+                token.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err(Error::UserInput(Text::new("args.unterminated_quote")));
+    }
+
+    if in_token {
+        tokens.push((token, !quoted));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_duration(input: &str) -> Option<Duration> {
+    let mut seconds = 0u64;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = number.parse().ok()?;
+        seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || seconds == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+// The inverse of `parse_duration`, e.g. 36 hours become "1d12h".
+pub fn format_duration(duration: Duration) -> String {
+    let mut seconds = duration.as_secs();
+    let mut output = String::new();
+
+    for (unit, size) in [
+        ('w', 7 * 24 * 60 * 60),
+        ('d', 24 * 60 * 60),
+        ('h', 60 * 60),
+        ('m', 60),
+        ('s', 1),
+    ] {
+        if seconds >= size {
+            output.push_str(&format!("{}{}", seconds / size, unit));
+            seconds %= size;
+        }
+    }
+
+    output
+}
+
+fn parse_mention(input: &str, prefix: &str) -> Option<u64> {
+    if let Ok(id) = input.parse() {
+        return Some(id);
+    }
+
+    input.strip_prefix(prefix)?.strip_suffix('>')?.parse().ok()
+}
+
+impl Args {
+    pub fn parse(input: &str) -> Result<Self> {
+        Ok(Self::from_tokens(tokenize(input)?))
+    }
+
+    pub fn from_tokens(tokens: impl IntoIterator<Item = (String, bool)>) -> Self {
+        let mut args = Args::default();
+
+        for (token, bare) in tokens {
+            match token.strip_prefix("--") {
+                Some(flag) if bare && !flag.is_empty() => {
+                    args.flags.insert(flag.to_string());
+                }
+                _ => args.positional.push(token),
+            }
+        }
+
+        args
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positional.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    pub fn as_strs(&self) -> Vec<&str> {
+        self.positional.iter().map(String::as_str).collect()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    fn required(&self, index: usize) -> Result<&str> {
+        self.get(index)
+            .ok_or_else(|| Error::UserInput(Text::new("args.missing").param("index", index + 1)))
+    }
+
+    pub fn integer(&self, index: usize, range: RangeInclusive<i64>) -> Result<i64> {
+        self.required(index)?
+            .parse()
+            .ok()
+            .filter(|x| range.contains(x))
+            .ok_or_else(|| {
+                Error::UserInput(
+                    Text::new("args.integer")
+                        .param("index", index + 1)
+                        .param("min", range.start())
+                        .param("max", range.end()),
+                )
+            })
+    }
+
+    pub fn user(&self, index: usize) -> Result<u64> {
+        let input = self.required(index)?;
+        parse_mention(input, "<@!")
+            .or_else(|| parse_mention(input, "<@"))
+            .ok_or_else(|| Error::UserInput(Text::new("args.user").param("index", index + 1)))
+    }
+
+    pub fn role(&self, index: usize) -> Result<u64> {
+        parse_mention(self.required(index)?, "<@&")
+            .ok_or_else(|| Error::UserInput(Text::new("args.role").param("index", index + 1)))
+    }
+
+    pub fn duration(&self, index: usize) -> Result<Duration> {
+        parse_duration(self.required(index)?)
+            .ok_or_else(|| Error::UserInput(Text::new("args.duration").param("index", index + 1)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let args = Args::parse(r#"set "hello world" it\'s --force '--literal'"#).unwrap();
+        assert_eq!(
+            args.as_strs(),
+            vec!["set", "hello world", "it's", "--literal"]
+        );
+        assert!(args.flag("force"));
+        assert!(!args.flag("literal"));
+        assert!(Args::parse(r#"set "unterminated"#).is_err());
+        assert!(Args::parse("trailing\\").is_err());
+        assert!(Args::parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_typed() {
+        let args = Args::parse("100 <#1> <@!2> <@&3> 1d12h 0").unwrap();
+        assert_eq!(args.integer(0, 1..=10000).unwrap(), 100);
+        assert!(args.integer(5, 1..=10000).is_err());
+        assert!(args.integer(6, 1..=10000).is_err());
+        assert!(args.user(1).is_err());
+        assert_eq!(args.user(2).unwrap(), 2);
+        assert_eq!(args.role(3).unwrap(), 3);
+        assert_eq!(args.duration(4).unwrap(), Duration::from_secs(36 * 60 * 60));
+        assert!(args.duration(0).is_err());
+        assert_eq!(format_duration(Duration::from_secs(36 * 60 * 60)), "1d12h");
+    }
+}
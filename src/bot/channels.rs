@@ -0,0 +1,56 @@
+use super::{Channel, ResponseCallbacks};
+use crate::error::Result;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// A channel Discord reported missing is asked for again after this long, in
+// case the bot only lost sight of it for a moment. Failed requests are not
+// remembered at all.
+const MISSING_TTL: Duration = Duration::from_secs(5 * 60);
+
+enum Entry {
+    Found(Channel),
+    Missing(Instant),
+}
+
+// Channels fetched so far, kept current by the channel and thread events.
+#[derive(Default)]
+pub struct Channels {
+    channels: Mutex<HashMap<u64, Entry>>,
+}
+
+impl Channels {
+    pub fn update(&self, channel: Channel) {
+        self.channels
+            .lock()
+            .unwrap()
+            .insert(channel.id, Entry::Found(channel));
+    }
+
+    pub fn forget(&self, channel_id: u64) {
+        self.channels.lock().unwrap().remove(&channel_id);
+    }
+
+    // None when the channel doesn't exist.
+    pub async fn get(
+        &self,
+        channel_id: u64,
+        callbacks: &dyn ResponseCallbacks,
+    ) -> Result<Option<Channel>> {
+        match self.channels.lock().unwrap().get(&channel_id) {
+            Some(Entry::Found(channel)) => return Ok(Some(channel.clone())),
+            Some(Entry::Missing(since)) if since.elapsed() < MISSING_TTL => return Ok(None),
+            _ => (),
+        }
+
+        let channel = callbacks.fetch_channel(channel_id).await?;
+        let entry = match &channel {
+            Some(channel) => Entry::Found(channel.clone()),
+            None => Entry::Missing(Instant::now()),
+        };
+        self.channels.lock().unwrap().insert(channel_id, entry);
+
+        Ok(channel)
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::{Error, Result};
+use diesel::r2d2::{ConnectionManager, Pool, PoolError};
+use diesel::{PgConnection, QueryResult};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Database {
+    pool: Pool<ConnectionManager<PgConnection>>,
+}
+
+impl Database {
+    pub fn connect(database_url: &str, pool_size: u32) -> Result<Self, PoolError> {
+        // Broken connections are dropped on checkout and replaced by the pool,
+        // which keeps the bot working across Postgres restarts.
+        let pool = Pool::builder()
+            .max_size(pool_size)
+            .connection_timeout(Duration::from_secs(10))
+            .test_on_check_out(true)
+            .build(ConnectionManager::new(database_url))?;
+
+        Ok(Database { pool })
+    }
+
+    pub async fn run<F, T>(&self, query: F) -> Result<T>
+    where
+        F: FnOnce(&PgConnection) -> QueryResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.pool.clone();
+
+        tokio::task::spawn_blocking(move || Ok(query(&*pool.get()?)?))
+            .await
+            .map_err(|error| Error::Database(error.into()))?
+    }
+}
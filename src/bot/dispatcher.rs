@@ -0,0 +1,81 @@
+use super::Bot;
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Sender};
+use tracing::error;
+use twilight_gateway::Event;
+use twilight_model::application::interaction::Interaction;
+
+const QUEUE_SIZE: usize = 64;
+
+pub struct Dispatcher {
+    queues: Vec<Sender<Event>>,
+}
+
+// Events sharing a key always land on the same worker, so events in one channel
+// are handled in the order they arrived.
+fn event_key(event: &Event) -> u64 {
+    match event {
+        Event::MessageCreate(msg) => msg.channel_id.0.into(),
+        Event::MessageUpdate(update) => update.channel_id.0.into(),
+        Event::MessageDelete(delete) => delete.channel_id.0.into(),
+        Event::MessageDeleteBulk(delete) => delete.channel_id.0.into(),
+        // Commands must be acknowledged within 3 seconds, so they can't all
+        // wait on one worker.
+        Event::InteractionCreate(interaction) => match &interaction.0 {
+            Interaction::ApplicationCommand(command) => command.channel_id.0.into(),
+            _ => 0,
+        },
+        Event::ChannelCreate(channel) => channel.id().0.into(),
+        Event::ChannelUpdate(channel) => channel.id().0.into(),
+        Event::ChannelDelete(channel) => channel.id().0.into(),
+        Event::ThreadCreate(thread) => thread.id().0.into(),
+        Event::ThreadUpdate(thread) => thread.id().0.into(),
+        Event::ThreadDelete(thread) => thread.id().0.into(),
+        _ => 0,
+    }
+}
+
+impl Dispatcher {
+    pub fn new(bot: Bot, workers: usize) -> Self {
+        let bot = Arc::new(bot);
+
+        let queues = (0..workers.max(1))
+            .map(|_| {
+                let (sender, mut receiver) = mpsc::channel(QUEUE_SIZE);
+                let bot = Arc::clone(&bot);
+
+                tokio::spawn(async move {
+                    while let Some(event) = receiver.recv().await {
+                        // A panicking handler must not take the worker, and
+                        // every channel keyed to it, down with it.
+                        if AssertUnwindSafe(bot.handle(event))
+                            .catch_unwind()
+                            .await
+                            .is_err()
+                        {
+                            error!("event handling panicked");
+                        }
+                    }
+                });
+
+                sender
+            })
+            .collect();
+
+        for index in 0..bot.timers() {
+            let bot = Arc::clone(&bot);
+            tokio::spawn(async move { bot.run_timer(index).await });
+        }
+
+        Dispatcher { queues }
+    }
+
+    pub async fn dispatch(&self, event: Event) {
+        let index = event_key(&event) % self.queues.len() as u64;
+        if self.queues[index as usize].send(event).await.is_err() {
+            error!(worker = index, "worker stopped, dropping event");
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use super::Database;
+use crate::error::Result;
+use crate::models::FeatureToggle;
+use crate::schema::feature_toggle::dsl as feature_toggle;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureScope {
+    Default,
+    Guild,
+    Channel,
+}
+
+#[derive(Default)]
+pub struct GuildFeatures(Vec<FeatureToggle>);
+
+impl GuildFeatures {
+    fn find(&self, feature: &str, channel_id: u64) -> Option<bool> {
+        self.0
+            .iter()
+            .find(|x| x.feature == feature && x.channel_id == channel_id as i64)
+            .map(|x| x.enabled)
+    }
+
+    // A channel toggle overrides the guild toggle; features are on unless
+    // switched off.
+    pub fn state(&self, feature: &str, channel_id: u64) -> (bool, FeatureScope) {
+        if let Some(enabled) = self.find(feature, channel_id) {
+            (enabled, FeatureScope::Channel)
+        } else if let Some(enabled) = self.find(feature, 0) {
+            (enabled, FeatureScope::Guild)
+        } else {
+            (true, FeatureScope::Default)
+        }
+    }
+
+    pub fn enabled(&self, feature: Option<&str>, channel_id: u64) -> bool {
+        feature.is_none_or(|feature| self.state(feature, channel_id).0)
+    }
+}
+
+#[derive(Default)]
+pub struct Features {
+    names: Vec<&'static str>,
+    guilds: Mutex<HashMap<u64, Arc<GuildFeatures>>>,
+}
+
+impl Features {
+    pub fn register(&mut self, name: &'static str) {
+        if !self.names.contains(&name) {
+            self.names.push(name);
+        }
+    }
+
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    pub fn forget(&self, guild_id: u64) {
+        self.guilds.lock().unwrap().remove(&guild_id);
+    }
+
+    pub async fn get(&self, guild_id: Option<u64>, db: &Database) -> Result<Arc<GuildFeatures>> {
+        let guild_id = match guild_id {
+            Some(guild_id) => guild_id,
+            None => return Ok(Arc::default()),
+        };
+
+        if let Some(features) = self.guilds.lock().unwrap().get(&guild_id) {
+            return Ok(features.clone());
+        }
+
+        let toggles = db
+            .run(move |conn| {
+                feature_toggle::feature_toggle
+                    .filter(feature_toggle::guild_id.eq(guild_id as i64))
+                    .load::<FeatureToggle>(conn)
+            })
+            .await?;
+        let features = Arc::new(GuildFeatures(toggles));
+
+        self.guilds
+            .lock()
+            .unwrap()
+            .insert(guild_id, features.clone());
+
+        Ok(features)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_state() {
+        use super::{FeatureScope, FeatureToggle, GuildFeatures};
+        let toggle = |channel_id, enabled| FeatureToggle {
+            guild_id: 1,
+            channel_id,
+            feature: "quote".to_string(),
+            enabled,
+        };
+        let features = GuildFeatures(vec![toggle(0, false), toggle(2, true)]);
+        assert_eq!(features.state("quote", 2), (true, FeatureScope::Channel));
+        assert_eq!(features.state("quote", 3), (false, FeatureScope::Guild));
+        assert_eq!(features.state("ping", 3), (true, FeatureScope::Default));
+        assert!(features.enabled(None, 3));
+        assert!(!features.enabled(Some("quote"), 3));
+    }
+}
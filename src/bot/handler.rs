@@ -1,16 +1,26 @@
-use super::{Channel, IncomingMessage, Message};
+use super::{
+    check_permissions, command_usage, Args, Channel, Channels, Database, Features, IncomingMessage,
+    Locale, Locales, Message, MessageDelete, MessageDeleteBulk, MessageUpdate, Prefix,
+    SlashCommand, Text, User,
+};
+use crate::error::{Error, Result};
 use async_trait::async_trait;
-use diesel::PgConnection;
-use std::error::Error;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use twilight_model::guild::Permissions;
 
 #[async_trait]
 pub trait ResponseCallbacks: Send + Sync {
-    async fn send_message(&self, _: Message) -> Result<(), Box<dyn Error>> {
+    async fn send_message(&self, _: Message) -> Result<()> {
         Ok(())
     }
 
-    async fn delete_message(&self, _: u64, _: u64) -> Result<(), Box<dyn Error>> {
+    async fn delete_message(&self, _: u64, _: u64) -> Result<()> {
+        Ok(())
+    }
+
+    // Deletes 2 to 100 messages at once; none of them may be older than two
+    // weeks.
+    async fn delete_messages(&self, _: u64, _: &[u64]) -> Result<()> {
         Ok(())
     }
 
@@ -18,80 +28,272 @@ pub trait ResponseCallbacks: Send + Sync {
         None
     }
 
-    async fn fetch_channel(&self, _: u64) -> Option<Channel> {
+    // None when the channel doesn't exist.
+    async fn fetch_channel(&self, _: u64) -> Result<Option<Channel>> {
+        Ok(None)
+    }
+
+    // Up to `limit` messages older than `before` (or the newest ones), newest
+    // first.
+    async fn fetch_history(&self, _: u64, _: Option<u64>, _: u64) -> Result<Vec<IncomingMessage>> {
+        Ok(Vec::new())
+    }
+
+    async fn fetch_permissions(&self, _: u64, _: u64) -> Option<Permissions> {
         None
     }
+
+    // The color of the member's highest colored role.
+    async fn fetch_member_color(&self, _: u64, _: u64) -> Option<u32> {
+        None
+    }
+
+    // Acknowledges an interaction; its replies then go out as followups,
+    // which Discord accepts for 15 minutes instead of 3 seconds.
+    async fn defer_interaction(&self, _: u64, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn followup_interaction(&self, _: &str, _: Message) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct AuthorFilter {
+    pub ignore_self: bool,
+    pub ignore_bots: bool,
+    pub ignore_webhooks: bool,
+}
+
+impl AuthorFilter {
+    pub const HUMANS: Self = AuthorFilter {
+        ignore_self: true,
+        ignore_bots: true,
+        ignore_webhooks: true,
+    };
+
+    pub fn accepts(&self, author: &User, current_user_id: Option<u64>) -> bool {
+        !(self.ignore_self && Some(author.id) == current_user_id
+            || self.ignore_bots && author.bot
+            || self.ignore_webhooks && author.webhook)
+    }
 }
 
+#[derive(Clone)]
 pub struct Context {
     pub callbacks: Arc<dyn ResponseCallbacks>,
-    pub db_conn: Arc<Mutex<PgConnection>>,
+    pub db: Database,
+    pub prefix: Arc<Prefix>,
+    pub commands: Arc<Vec<Arc<dyn CommandHandler>>>,
+    pub owners: Arc<Vec<u64>>,
+    pub current_user_id: Option<u64>,
+    pub locales: Arc<Locales>,
+    pub features: Arc<Features>,
+    pub channels: Arc<Channels>,
+}
+
+impl Context {
+    pub async fn locale(&self, guild_id: Option<u64>) -> Result<Locale> {
+        self.locales.get(guild_id, &self.db).await
+    }
+
+    pub async fn text(&self, guild_id: Option<u64>, text: Text) -> Result<String> {
+        Ok(text.render(self.locale(guild_id).await?))
+    }
+
+    pub async fn channel(&self, channel_id: u64) -> Result<Option<Channel>> {
+        self.channels.get(channel_id, &*self.callbacks).await
+    }
 }
 
 #[async_trait]
-pub trait MessageHandler {
-    async fn on_message(
-        &mut self,
-        message: &IncomingMessage,
+pub trait MessageHandler: Send + Sync {
+    fn author_filter(&self) -> AuthorFilter {
+        AuthorFilter::HUMANS
+    }
+
+    // Name under which guild admins can switch the handler off.
+    fn feature(&self) -> Option<&'static str> {
+        None
+    }
+
+    async fn on_message(&self, message: &IncomingMessage, context: &Context) -> Result<()>;
+}
+
+#[async_trait]
+pub trait MessageUpdateHandler: Send + Sync {
+    async fn on_message_update(&self, update: &MessageUpdate, context: &Context) -> Result<()>;
+}
+
+#[async_trait]
+pub trait MessageDeleteHandler: Send + Sync {
+    async fn on_message_delete(&self, delete: &MessageDelete, context: &Context) -> Result<()>;
+}
+
+#[async_trait]
+pub trait MessageDeleteBulkHandler: Send + Sync {
+    async fn on_message_delete_bulk(
+        &self,
+        delete: &MessageDeleteBulk,
         context: &Context,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<()>;
 }
 
-pub struct FnMessageHandler<T: FnMut(&IncomingMessage) -> Option<Message> + Send + Sync>(pub T);
+#[async_trait]
+pub trait ChannelDeleteHandler: Send + Sync {
+    async fn on_channel_delete(&self, channel: &Channel, context: &Context) -> Result<()>;
+}
+
+// Runs periodically rather than in response to an event.
+#[async_trait]
+pub trait TimerHandler: Send + Sync {
+    async fn on_timer(&self, context: &Context) -> Result<()>;
+}
 
 #[async_trait]
-impl<T> MessageHandler for FnMessageHandler<T>
-where
-    T: FnMut(&IncomingMessage) -> Option<Message> + Send + Sync,
-{
-    async fn on_message(
-        &mut self,
+pub trait CommandHandler: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    // Argument forms, one per line, shown after the command name in help.
+    fn usage(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    // Catalog key of a one-line description.
+    fn description(&self) -> &'static str;
+
+    fn accepts(&self, command_name: &str) -> bool {
+        command_name == self.name() || self.aliases().contains(&command_name)
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        Permissions::empty()
+    }
+
+    fn feature(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        None
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
         message: &IncomingMessage,
         context: &Context,
-    ) -> Result<(), Box<dyn Error>> {
-        if let Some(reply) = self.0(message) {
-            context.callbacks.send_message(reply).await
-        } else {
-            Ok(())
-        }
-    }
+    ) -> Result<()>;
 }
 
 #[async_trait]
-pub trait CommandHandler: Send + Sync {
-    fn accepts(&self, command_name: &str) -> bool;
+impl<T: CommandHandler + ?Sized> CommandHandler for Arc<T> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        (**self).aliases()
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        (**self).usage()
+    }
+
+    fn description(&self) -> &'static str {
+        (**self).description()
+    }
+
+    fn accepts(&self, command_name: &str) -> bool {
+        (**self).accepts(command_name)
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        (**self).required_permissions()
+    }
+
+    fn feature(&self) -> Option<&'static str> {
+        (**self).feature()
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        (**self).slash_command()
+    }
 
     async fn handler(
-        &mut self,
-        args: &[&str],
+        &self,
+        args: &Args,
         message: &IncomingMessage,
         context: &Context,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<()> {
+        (**self).handler(args, message, context).await
+    }
 }
 
-fn parse_command(command: &str) -> Option<(&str, Vec<&str>)> {
-    let mut parts = command.split(' ').filter(|x| !x.is_empty());
+// Runs a command once its author is allowed to, turning a rejected argument
+// list into the command's usage.
+pub async fn run_command<T: CommandHandler + ?Sized>(
+    handler: &T,
+    args: &Args,
+    incoming: &IncomingMessage,
+    context: &Context,
+) -> Result<()> {
+    check_permissions(handler.required_permissions(), &incoming.1, context).await?;
 
-    if let Some(command) = parts.next() {
-        let args = parts.collect();
-        Some((command, args))
-    } else {
+    match handler.handler(args, incoming, context).await {
+        Err(Error::Usage) => {
+            let (_, message) = incoming;
+            let prefix = context.prefix.get(message.guild_id, &context.db).await?;
+            let locale = context.locale(message.guild_id).await?;
+            Err(Error::UserInput(
+                Text::new("command.invalid_usage")
+                    .param("usage", command_usage(handler, &prefix, locale)),
+            ))
+        }
+        result => result,
+    }
+}
+
+// Only the command name is split off here; the rest is tokenized once a
+// handler has accepted the command, so malformed arguments are reported by
+// that handler alone.
+fn parse_command(command: &str) -> Option<(&str, &str)> {
+    let command = command.trim_start();
+
+    if command.is_empty() {
         None
+    } else {
+        Some(
+            command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, "")),
+        )
     }
 }
 
 #[async_trait]
 impl<T: CommandHandler> MessageHandler for T {
-    async fn on_message(
-        &mut self,
-        incoming: &IncomingMessage,
-        context: &Context,
-    ) -> Result<(), Box<dyn Error>> {
+    fn feature(&self) -> Option<&'static str> {
+        CommandHandler::feature(self)
+    }
+
+    async fn on_message(&self, incoming: &IncomingMessage, context: &Context) -> Result<()> {
         let (_, message) = incoming;
 
-        if let Some((command, args)) = parse_command(&message.content) {
+        let content = context
+            .prefix
+            .strip(&message.content, message.guild_id, &context.db)
+            .await?;
+
+        if let Some((command, args)) = content.and_then(parse_command) {
             if self.accepts(command) {
-                return self.handler(&args, incoming, context).await;
+                let args = Args::parse(args)?;
+                return run_command(self, &args, incoming, context).await;
             }
         }
 
@@ -103,16 +305,47 @@ impl<T: CommandHandler> MessageHandler for T {
 mod test {
     #[test]
     fn test_parse_command() {
-        use super::parse_command;
+        use super::{parse_command, Args};
+        let parse = |command| {
+            parse_command(command)
+                .map(|(command, args)| (command, Args::parse(args).unwrap().as_strs().join(",")))
+        };
         assert_eq!(
-            parse_command("command arg1 arg2"),
-            Some(("command", vec!["arg1", "arg2"])),
+            parse("command arg1 arg2"),
+            Some(("command", "arg1,arg2".to_string())),
         );
         assert_eq!(
-            parse_command(" command  arg1  arg2 "),
-            Some(("command", vec!["arg1", "arg2"])),
+            parse(" command  arg1  arg2 "),
+            Some(("command", "arg1,arg2".to_string())),
         );
-        assert_eq!(parse_command("command"), Some(("command", vec![])),);
-        assert_eq!(parse_command(" "), None);
+        assert_eq!(parse("command"), Some(("command", String::new())),);
+        assert_eq!(parse(" "), None);
+    }
+
+    #[test]
+    fn test_author_filter() {
+        use super::{AuthorFilter, User};
+        let user = |id, bot, webhook| User {
+            id,
+            bot,
+            webhook,
+            ..User::default()
+        };
+        assert!(AuthorFilter::HUMANS.accepts(&user(1, false, false), Some(2)));
+        assert!(!AuthorFilter::HUMANS.accepts(&user(2, false, false), Some(2)));
+        assert!(!AuthorFilter::HUMANS.accepts(&user(1, true, false), Some(2)));
+        assert!(!AuthorFilter::HUMANS.accepts(&user(1, false, true), Some(2)));
+        let not_self = AuthorFilter {
+            ignore_self: true,
+            ignore_bots: false,
+            ignore_webhooks: false,
+        };
+        assert!(not_self.accepts(&user(1, true, true), Some(2)));
+        assert!(!not_self.accepts(&user(2, true, false), Some(2)));
+        let all = AuthorFilter {
+            ignore_self: false,
+            ..not_self
+        };
+        assert!(all.accepts(&user(2, true, false), Some(2)));
     }
 }
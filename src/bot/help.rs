@@ -0,0 +1,105 @@
+use super::{
+    Args, CommandHandler, CommandOption, CommandOptionKind, Context, IncomingMessage, Locale,
+    SlashCommand, Text,
+};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+
+pub fn command_usage<T: CommandHandler + ?Sized>(
+    handler: &T,
+    prefix: &str,
+    locale: Locale,
+) -> String {
+    let mut text = format!(
+        "**{}{}** — {}",
+        prefix,
+        handler.name(),
+        Text::new(handler.description()).render(locale)
+    );
+
+    if !handler.aliases().is_empty() {
+        text += "\n";
+        text += &Text::new("help.aliases")
+            .param("aliases", handler.aliases().join(", "))
+            .render(locale);
+    }
+
+    for usage in handler.usage() {
+        text += &format!("\n`{}{} {}`", prefix, handler.name(), usage);
+    }
+
+    text
+}
+
+pub struct Help;
+
+#[async_trait]
+impl CommandHandler for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        &["[command]"]
+    }
+
+    fn description(&self) -> &'static str {
+        "help.description"
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        Some(SlashCommand::new(
+            self.name(),
+            self.description(),
+            vec![CommandOption::value(
+                "command",
+                "help.slash.command",
+                CommandOptionKind::String,
+                false,
+            )],
+        ))
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
+        (_, message): &IncomingMessage,
+        context: &Context,
+    ) -> Result<()> {
+        let prefix = context.prefix.get(message.guild_id, &context.db).await?;
+        let locale = context.locale(message.guild_id).await?;
+
+        let reply = match args.as_strs()[..] {
+            [] => {
+                let mut reply = Text::new("help.list").render(locale);
+                for handler in context.commands.iter() {
+                    reply += &format!(
+                        "\n`{}{}` — {}",
+                        prefix,
+                        handler.name(),
+                        Text::new(handler.description()).render(locale)
+                    );
+                }
+                reply += "\n";
+                reply += &Text::new("help.hint")
+                    .param("prefix", &prefix)
+                    .render(locale);
+                reply
+            }
+            [name] => {
+                let name = name.strip_prefix(prefix.as_str()).unwrap_or(name);
+                let handler = context
+                    .commands
+                    .iter()
+                    .find(|handler| handler.accepts(name))
+                    .ok_or_else(|| {
+                        Error::UserInput(Text::new("help.unknown").param("name", name))
+                    })?;
+                command_usage(handler.as_ref(), &prefix, locale)
+            }
+            _ => return Err(Error::Usage),
+        };
+
+        context.callbacks.send_message(message.reply(&reply)).await
+    }
+}
@@ -0,0 +1,146 @@
+use super::{Channel, IncomingMessage, Message, ResponseCallbacks};
+use crate::error::Result;
+use crate::metrics::{DISCORD_API_CALLS, DISCORD_API_DURATION};
+use async_trait::async_trait;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{debug, info_span, warn, Instrument};
+use twilight_model::guild::Permissions;
+
+pub struct InstrumentedCallbacks<T>(pub T);
+
+fn record(call: &str, outcome: &str, elapsed: Duration) -> u64 {
+    DISCORD_API_CALLS.with_label_values(&[call, outcome]).inc();
+    DISCORD_API_DURATION
+        .with_label_values(&[call])
+        .observe(elapsed.as_secs_f64());
+    elapsed.as_millis() as u64
+}
+
+async fn observe<T>(call: &str, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let start = Instant::now();
+    let result = future.await;
+
+    match &result {
+        Ok(_) => {
+            let elapsed_ms = record(call, "ok", start.elapsed());
+            debug!(elapsed_ms, outcome = "ok", "{} finished", call);
+        }
+        Err(error) => {
+            let elapsed_ms = record(call, "error", start.elapsed());
+            warn!(elapsed_ms, outcome = "error", %error, "{} failed", call);
+        }
+    }
+
+    result
+}
+
+async fn observe_fetch<T>(call: &str, future: impl Future<Output = Option<T>>) -> Option<T> {
+    let start = Instant::now();
+    let result = future.await;
+    let outcome = if result.is_some() { "found" } else { "missing" };
+    let elapsed_ms = record(call, outcome, start.elapsed());
+
+    debug!(elapsed_ms, outcome, "{} finished", call);
+
+    result
+}
+
+#[async_trait]
+impl<T: ResponseCallbacks> ResponseCallbacks for InstrumentedCallbacks<T> {
+    async fn send_message(&self, message: Message) -> Result<()> {
+        let span = info_span!("send_message", channel_id = message.channel_id);
+        observe("send_message", self.0.send_message(message))
+            .instrument(span)
+            .await
+    }
+
+    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<()> {
+        let span = info_span!("delete_message", channel_id, message_id);
+        observe(
+            "delete_message",
+            self.0.delete_message(channel_id, message_id),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn delete_messages(&self, channel_id: u64, message_ids: &[u64]) -> Result<()> {
+        let span = info_span!("delete_messages", channel_id, count = message_ids.len());
+        observe(
+            "delete_messages",
+            self.0.delete_messages(channel_id, message_ids),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn fetch_message(&self, channel_id: u64, message_id: u64) -> Option<Message> {
+        let span = info_span!("fetch_message", channel_id, message_id);
+        observe_fetch(
+            "fetch_message",
+            self.0.fetch_message(channel_id, message_id),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn fetch_channel(&self, channel_id: u64) -> Result<Option<Channel>> {
+        let span = info_span!("fetch_channel", channel_id);
+        observe("fetch_channel", self.0.fetch_channel(channel_id))
+            .instrument(span)
+            .await
+    }
+
+    async fn fetch_history(
+        &self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<IncomingMessage>> {
+        let span = info_span!("fetch_history", channel_id, before, limit);
+        observe(
+            "fetch_history",
+            self.0.fetch_history(channel_id, before, limit),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn fetch_permissions(&self, guild_id: u64, user_id: u64) -> Option<Permissions> {
+        let span = info_span!("fetch_permissions", guild_id, user_id);
+        observe_fetch(
+            "fetch_permissions",
+            self.0.fetch_permissions(guild_id, user_id),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn fetch_member_color(&self, guild_id: u64, user_id: u64) -> Option<u32> {
+        let span = info_span!("fetch_member_color", guild_id, user_id);
+        observe_fetch(
+            "fetch_member_color",
+            self.0.fetch_member_color(guild_id, user_id),
+        )
+        .instrument(span)
+        .await
+    }
+
+    async fn defer_interaction(&self, id: u64, token: &str) -> Result<()> {
+        let span = info_span!("defer_interaction", interaction_id = id);
+        observe("defer_interaction", self.0.defer_interaction(id, token))
+            .instrument(span)
+            .await
+    }
+
+    async fn followup_interaction(&self, token: &str, message: Message) -> Result<()> {
+        let span = info_span!("followup_interaction");
+        observe(
+            "followup_interaction",
+            self.0.followup_interaction(token, message),
+        )
+        .instrument(span)
+        .await
+    }
+}
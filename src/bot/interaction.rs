@@ -0,0 +1,87 @@
+use super::{Channel, IncomingMessage, Interaction, Message, ResponseCallbacks};
+use crate::error::Result;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use twilight_model::guild::Permissions;
+
+// Routes replies to the interaction's channel through followups to the
+// deferred response, so command handlers answer slash commands the same way
+// they answer text commands.
+pub struct InteractionCallbacks {
+    callbacks: Arc<dyn ResponseCallbacks>,
+    interaction: Interaction,
+    responded: AtomicBool,
+}
+
+impl InteractionCallbacks {
+    pub fn new(callbacks: Arc<dyn ResponseCallbacks>, interaction: Interaction) -> Self {
+        InteractionCallbacks {
+            callbacks,
+            interaction,
+            responded: AtomicBool::new(false),
+        }
+    }
+
+    pub fn responded(&self) -> bool {
+        self.responded.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl ResponseCallbacks for InteractionCallbacks {
+    async fn send_message(&self, message: Message) -> Result<()> {
+        if message.channel_id != self.interaction.channel_id {
+            return self.callbacks.send_message(message).await;
+        }
+
+        self.responded.store(true, Ordering::SeqCst);
+        self.followup_interaction(&self.interaction.token, message)
+            .await
+    }
+
+    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<()> {
+        self.callbacks.delete_message(channel_id, message_id).await
+    }
+
+    async fn delete_messages(&self, channel_id: u64, message_ids: &[u64]) -> Result<()> {
+        self.callbacks
+            .delete_messages(channel_id, message_ids)
+            .await
+    }
+
+    async fn fetch_message(&self, channel_id: u64, message_id: u64) -> Option<Message> {
+        self.callbacks.fetch_message(channel_id, message_id).await
+    }
+
+    async fn fetch_channel(&self, channel_id: u64) -> Result<Option<Channel>> {
+        self.callbacks.fetch_channel(channel_id).await
+    }
+
+    async fn fetch_history(
+        &self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<IncomingMessage>> {
+        self.callbacks
+            .fetch_history(channel_id, before, limit)
+            .await
+    }
+
+    async fn fetch_permissions(&self, guild_id: u64, user_id: u64) -> Option<Permissions> {
+        self.callbacks.fetch_permissions(guild_id, user_id).await
+    }
+
+    async fn fetch_member_color(&self, guild_id: u64, user_id: u64) -> Option<u32> {
+        self.callbacks.fetch_member_color(guild_id, user_id).await
+    }
+
+    async fn defer_interaction(&self, id: u64, token: &str) -> Result<()> {
+        self.callbacks.defer_interaction(id, token).await
+    }
+
+    async fn followup_interaction(&self, token: &str, message: Message) -> Result<()> {
+        self.callbacks.followup_interaction(token, message).await
+    }
+}
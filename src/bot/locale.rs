@@ -0,0 +1,591 @@
+use super::Database;
+use crate::error::Result;
+use crate::schema::guild_config::dsl as guild_config;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::Ja, Locale::En];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|x| x.code() == code)
+    }
+}
+
+// key, ja, en
+static MESSAGES: &[(&str, &str, &str)] = &[
+    (
+        "args.trailing_escape",
+        "末尾に不正なエスケープがあります。",
+        "The arguments end with a dangling escape.",
+    ),
+    (
+        "args.unterminated_quote",
+        "引用符が閉じられていません。",
+        "A quote is not closed.",
+    ),
+    (
+        "args.missing",
+        "{index}番目の引数が必要です。",
+        "Argument {index} is required.",
+    ),
+    (
+        "args.integer",
+        "{index}番目の引数は{min}以上{max}以下の整数で指定してください。",
+        "Argument {index} must be an integer from {min} to {max}.",
+    ),
+    (
+        "args.user",
+        "{index}番目の引数にはユーザーを指定してください。",
+        "Argument {index} must be a user.",
+    ),
+    (
+        "args.role",
+        "{index}番目の引数にはロールを指定してください。",
+        "Argument {index} must be a role.",
+    ),
+    (
+        "args.duration",
+        "{index}番目の引数は 30m, 24h, 7d のような期間で指定してください。",
+        "Argument {index} must be a duration such as 30m, 24h or 7d.",
+    ),
+    (
+        "command.invalid",
+        "不正なコマンドです。",
+        "Invalid command.",
+    ),
+    (
+        "command.invalid_usage",
+        "不正なコマンドです。\n{usage}",
+        "Invalid command.\n{usage}",
+    ),
+    ("command.done", "完了しました。", "Done."),
+    (
+        "command.failed",
+        "エラーが発生しました。",
+        "Something went wrong.",
+    ),
+    (
+        "command.guild_only",
+        "サーバー内でのみ使用できます。",
+        "This command can only be used in a server.",
+    ),
+    (
+        "help.description",
+        "コマンドの一覧と使い方を表示します",
+        "Lists commands and shows their usage",
+    ),
+    ("help.aliases", "別名: {aliases}", "Aliases: {aliases}"),
+    ("help.list", "コマンド一覧:", "Commands:"),
+    (
+        "help.hint",
+        "`{prefix}help <コマンド>` で使い方を表示します。",
+        "Use `{prefix}help <command>` to show a command's usage.",
+    ),
+    (
+        "help.unknown",
+        "コマンド「{name}」は存在しません。",
+        "There is no command named \"{name}\".",
+    ),
+    (
+        "help.slash.command",
+        "使い方を表示するコマンド",
+        "Command to show the usage of",
+    ),
+    (
+        "permission.missing",
+        "このコマンドを実行するには「{permissions}」の権限が必要です。",
+        "You need the {permissions} permission to run this command.",
+    ),
+    ("permission.separator", "、", ", "),
+    ("permission.administrator", "管理者", "Administrator"),
+    ("permission.manage_guild", "サーバー管理", "Manage Server"),
+    (
+        "permission.manage_channels",
+        "チャンネルの管理",
+        "Manage Channels",
+    ),
+    (
+        "permission.manage_messages",
+        "メッセージの管理",
+        "Manage Messages",
+    ),
+    ("permission.manage_roles", "ロールの管理", "Manage Roles"),
+    (
+        "ping.description",
+        "応答を確認します",
+        "Checks that the bot responds",
+    ),
+    ("ping.pong", "pong!", "pong!"),
+    (
+        "meslimit.description",
+        "チャンネルに残すメッセージ数を設定します",
+        "Sets how many messages to keep in the channel",
+    ),
+    ("meslimit.enabled", "有効化しました。", "Enabled."),
+    ("meslimit.disabled", "無効化しました。", "Disabled."),
+    (
+        "meslimit.slash.enable",
+        "古いメッセージの自動削除を有効化します",
+        "Delete old messages automatically",
+    ),
+    (
+        "meslimit.slash.age",
+        "一定期間より古いメッセージを削除します",
+        "Delete messages older than a given age",
+    ),
+    (
+        "meslimit.slash.disable",
+        "自動削除を無効化します",
+        "Stop deleting messages",
+    ),
+    (
+        "meslimit.slash.exempt",
+        "削除の対象外にするメッセージを設定します",
+        "Choose messages that are never deleted",
+    ),
+    (
+        "meslimit.slash.archive",
+        "削除前にメッセージを保存するかを設定します",
+        "Choose whether messages are saved before deletion",
+    ),
+    (
+        "meslimit.slash.threads",
+        "スレッドにも同じ設定を適用するかを設定します",
+        "Choose whether threads follow this setting",
+    ),
+    (
+        "meslimit.slash.export",
+        "保存したメッセージを書き出します",
+        "Export saved messages",
+    ),
+    (
+        "meslimit.slash.reset",
+        "設定を削除し、上位の設定を引き継ぎます",
+        "Remove the setting and inherit the broader one",
+    ),
+    ("meslimit.slash.guild", "サーバー全体の既定値を設定します", "Set the server-wide default"),
+    ("meslimit.slash.category", "このチャンネルのカテゴリーに設定します", "Set it for this channel's category"),
+    ("meslimit.slash.status", "現在の設定と状態を表示します", "Show the current setting and state"),
+    ("meslimit.slash.dryrun", "削除されるメッセージ数を確認します", "Count the messages that would be deleted"),
+    (
+        "meslimit.slash.size",
+        "残すメッセージ数 (1〜10000)",
+        "Number of messages to keep (1-10000)",
+    ),
+    (
+        "meslimit.slash.duration",
+        "残す期間 (30m, 24h, 7d など)",
+        "How long to keep messages (30m, 24h, 7d, ...)",
+    ),
+    (
+        "meslimit.slash.backfill",
+        "既存のメッセージも対象にします",
+        "Include the messages already in the channel",
+    ),
+    (
+        "meslimit.slash.limit",
+        "解除する制限 (count, age)。省略時はすべて",
+        "Limit to remove (count, age); all if omitted",
+    ),
+    (
+        "meslimit.slash.exempt_kind",
+        "pinned, attachments, user, role",
+        "pinned, attachments, user, role",
+    ),
+    (
+        "meslimit.slash.exempt_value",
+        "on/off、またはユーザー・ロール",
+        "on/off, or a user or role",
+    ),
+    (
+        "meslimit.slash.remove",
+        "ユーザー・ロールを対象外から外します",
+        "Remove the user or role from the exemptions",
+    ),
+    ("meslimit.slash.switch", "on または off", "on or off"),
+    (
+        "meslimit.slash.from",
+        "開始日 (2024-01-31 の形式、UTC)",
+        "First day (like 2024-01-31, UTC)",
+    ),
+    (
+        "meslimit.slash.to",
+        "終了日 (2024-01-31 の形式、UTC)",
+        "Last day (like 2024-01-31, UTC)",
+    ),
+    (
+        "meslimit.updated",
+        "設定を変更しました。",
+        "Settings updated.",
+    ),
+    (
+        "meslimit.archive_enabled",
+        "保存を有効にしました。添付ファイルはリンクのみ保存され、メッセージの削除後は開けなくなります。",
+        "Archiving enabled. Attachments are kept as links only, which stop working once the message is deleted.",
+    ),
+    (
+        "meslimit.backfill_started",
+        "既存のメッセージを読み込んでいます…",
+        "Reading the existing messages…",
+    ),
+    (
+        "meslimit.backfill_progress",
+        "{count}件のメッセージを読み込みました…",
+        "Read {count} messages so far…",
+    ),
+    (
+        "meslimit.backfill_capped",
+        "最新の{count}件で読み込みを打ち切りました。それより古いメッセージは記録されません。",
+        "Stopped reading after the latest {count} messages; older ones are not tracked.",
+    ),
+    (
+        "meslimit.backfill_scope",
+        "`--backfill`は`--guild`や`--category`と一緒には使えません。",
+        "`--backfill` can't be combined with `--guild` or `--category`.",
+    ),
+    (
+        "meslimit.backfill_running",
+        "このチャンネルは既に読み込み中です。",
+        "This channel is already being backfilled.",
+    ),
+    (
+        "meslimit.backfill_done",
+        "既存のメッセージを{recorded}件記録し、{deleted}件を削除しました。",
+        "Recorded {recorded} existing messages and deleted {deleted}.",
+    ),
+    (
+        "meslimit.date",
+        "{index}番目の引数は 2024-01-31 のような日付で指定してください。",
+        "Argument {index} must be a date such as 2024-01-31.",
+    ),
+    (
+        "meslimit.date_range",
+        "日付は2015-01-01から2084-09-01までの間で指定してください。",
+        "Dates must be between 2015-01-01 and 2084-09-01.",
+    ),
+    (
+        "meslimit.date_order",
+        "開始日は終了日より前にしてください。",
+        "The start date must not be after the end date.",
+    ),
+    (
+        "meslimit.exported",
+        "{count}件のメッセージを書き出しました。",
+        "Exported {count} messages.",
+    ),
+    (
+        "meslimit.export_empty",
+        "指定した期間に保存されたメッセージはありません。",
+        "No messages were archived in that period.",
+    ),
+    (
+        "meslimit.export_too_large",
+        "書き出すファイルが大きすぎます。期間を短くしてください。",
+        "The export is too large to upload. Try a shorter period.",
+    ),
+    (
+        "meslimit.status",
+        "適用範囲: {scope}\n件数の上限: {size}\n期間の上限: {age}\n記録中のメッセージ: {tracked}件\n最も古い記録: {oldest}\n削除待ち: {pending}件 (失敗: {dead}件)",
+        "Applies from: {scope}\nCount limit: {size}\nAge limit: {age}\nTracked messages: {tracked}\nOldest tracked: {oldest}\nPending deletions: {pending} ({dead} failed)",
+    ),
+    ("meslimit.status_none", "なし", "none"),
+    ("meslimit.scope.channel", "チャンネル設定", "channel setting"),
+    ("meslimit.scope.category", "カテゴリー設定", "category setting"),
+    ("meslimit.scope.parent", "親チャンネル設定", "parent channel setting"),
+    ("meslimit.scope.guild", "サーバー設定", "server setting"),
+    (
+        "meslimit.reset",
+        "設定を削除しました。上位の設定が適用されます。",
+        "Setting removed; broader settings apply again.",
+    ),
+    (
+        "meslimit.no_category",
+        "このチャンネルはカテゴリーに属していません。",
+        "This channel is not in a category.",
+    ),
+    (
+        "meslimit.dryrun",
+        "記録中の{tracked}件のうち{count}件が削除されます。",
+        "{count} of {tracked} tracked messages would be deleted.",
+    ),
+    (
+        "meslimit.dryrun_scanned",
+        "まだ記録がないため直近の{scanned}件から数えました。{count}件が削除されます。`--backfill`を付けて有効にすると既存のメッセージも記録されます。",
+        "Nothing is tracked here yet, so the latest {scanned} messages were checked: {count} would be deleted. Enable with `--backfill` to track the existing messages too.",
+    ),
+    (
+        "meslimit.dryrun_untracked",
+        "このチャンネルのメッセージはまだ記録されていません。`--backfill`を付けて有効にすると既存のメッセージも記録されます。",
+        "No messages are tracked in this channel yet. Enable with `--backfill` to track the existing messages.",
+    ),
+    (
+        "meslimit.not_enabled",
+        "このチャンネルでは自動削除が有効になっていません。",
+        "Automatic deletion is not enabled in this channel.",
+    ),
+    (
+        "prefix.description",
+        "このサーバーのコマンドプレフィックスを設定します",
+        "Sets the command prefix for this server",
+    ),
+    (
+        "prefix.length",
+        "プレフィックスは{min}文字以上{max}文字以下で指定してください。",
+        "The prefix must be {min} to {max} characters long.",
+    ),
+    (
+        "prefix.changed",
+        "プレフィックスを変更しました。",
+        "The prefix has been changed.",
+    ),
+    (
+        "prefix.reset",
+        "プレフィックスを既定に戻しました。",
+        "The prefix has been reset to the default.",
+    ),
+    (
+        "prefix.slash.set",
+        "プレフィックスを変更します",
+        "Change the prefix",
+    ),
+    (
+        "prefix.slash.prefix",
+        "新しいプレフィックス",
+        "The new prefix",
+    ),
+    (
+        "prefix.slash.reset",
+        "既定のプレフィックスに戻します",
+        "Go back to the default prefix",
+    ),
+    (
+        "locale.description",
+        "このサーバーで使う言語を設定します",
+        "Sets the language used in this server",
+    ),
+    (
+        "locale.unknown",
+        "対応していない言語です。対応言語: {locales}",
+        "Unsupported language. Available: {locales}",
+    ),
+    (
+        "locale.changed",
+        "言語を日本語に変更しました。",
+        "Language set to English.",
+    ),
+    (
+        "locale.reset",
+        "言語を既定に戻しました。",
+        "The language has been reset to the default.",
+    ),
+    (
+        "locale.slash.set",
+        "言語を変更します",
+        "Change the language",
+    ),
+    (
+        "locale.slash.locale",
+        "言語コード (ja, en)",
+        "Language code (ja, en)",
+    ),
+    (
+        "locale.slash.reset",
+        "既定の言語に戻します",
+        "Go back to the default language",
+    ),
+    (
+        "features.description",
+        "機能の有効・無効を切り替えます",
+        "Turns features on or off",
+    ),
+    (
+        "features.list",
+        "このチャンネルでの機能の状態:",
+        "Features in this channel:",
+    ),
+    ("features.on", "有効", "enabled"),
+    ("features.off", "無効", "disabled"),
+    ("features.scope.default", "既定", "default"),
+    ("features.scope.guild", "サーバー設定", "server setting"),
+    (
+        "features.scope.channel",
+        "チャンネル設定",
+        "channel setting",
+    ),
+    (
+        "features.unknown",
+        "機能「{name}」は存在しません。対応: {features}",
+        "There is no feature named \"{name}\". Available: {features}",
+    ),
+    (
+        "features.updated",
+        "「{name}」を{state}にしました。",
+        "{name} is now {state}.",
+    ),
+    (
+        "features.reset",
+        "「{name}」の設定を解除しました。",
+        "{name} is back to its inherited setting.",
+    ),
+    (
+        "features.command_disabled",
+        "この機能はこのチャンネルでは無効です。",
+        "This feature is disabled in this channel.",
+    ),
+    (
+        "features.slash.list",
+        "機能の状態を表示します",
+        "Show which features are on",
+    ),
+    (
+        "features.slash.enable",
+        "機能を有効化します",
+        "Turn a feature on",
+    ),
+    (
+        "features.slash.disable",
+        "機能を無効化します",
+        "Turn a feature off",
+    ),
+    (
+        "features.slash.reset",
+        "機能の設定を解除します",
+        "Remove a feature's setting",
+    ),
+    ("features.slash.feature", "機能名", "Feature name"),
+    (
+        "features.slash.channel",
+        "このチャンネルだけに適用します",
+        "Apply to this channel only",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub key: &'static str,
+    pub params: Vec<(&'static str, String)>,
+}
+
+impl Text {
+    pub fn new(key: &'static str) -> Self {
+        Text {
+            key,
+            params: Vec::new(),
+        }
+    }
+
+    pub fn param(mut self, name: &'static str, value: impl Display) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    // Unknown keys render as the key itself so a missing entry is visible
+    // rather than fatal.
+    pub fn render(&self, locale: Locale) -> String {
+        let template = MESSAGES
+            .iter()
+            .find(|(key, _, _)| *key == self.key)
+            .map(|(_, ja, en)| match locale {
+                Locale::Ja => *ja,
+                Locale::En => *en,
+            })
+            .unwrap_or(self.key);
+
+        self.params
+            .iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+}
+
+pub struct Locales {
+    default: Locale,
+    guilds: Mutex<HashMap<u64, Option<Locale>>>,
+}
+
+impl Locales {
+    pub fn new(default: Locale) -> Self {
+        Locales {
+            default,
+            guilds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn forget(&self, guild_id: u64) {
+        self.guilds.lock().unwrap().remove(&guild_id);
+    }
+
+    async fn guild_locale(&self, guild_id: u64, db: &Database) -> Result<Option<Locale>> {
+        if let Some(locale) = self.guilds.lock().unwrap().get(&guild_id) {
+            return Ok(*locale);
+        }
+
+        let locale = db
+            .run(move |conn| {
+                guild_config::guild_config
+                    .select(guild_config::locale)
+                    .find(guild_id as i64)
+                    .first::<Option<String>>(conn)
+                    .optional()
+            })
+            .await?
+            .flatten()
+            .and_then(|x| Locale::from_code(&x));
+
+        self.guilds.lock().unwrap().insert(guild_id, locale);
+
+        Ok(locale)
+    }
+
+    pub async fn get(&self, guild_id: Option<u64>, db: &Database) -> Result<Locale> {
+        let locale = match guild_id {
+            Some(guild_id) => self.guild_locale(guild_id, db).await?,
+            None => None,
+        };
+
+        Ok(locale.unwrap_or(self.default))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_render() {
+        use super::{Locale, Text, MESSAGES};
+        let text = Text::new("args.integer")
+            .param("index", 2)
+            .param("min", 1)
+            .param("max", 10000);
+        assert_eq!(
+            text.render(Locale::Ja),
+            "2番目の引数は1以上10000以下の整数で指定してください。"
+        );
+        assert_eq!(
+            text.render(Locale::En),
+            "Argument 2 must be an integer from 1 to 10000."
+        );
+        assert_eq!(Text::new("no.such.key").render(Locale::En), "no.such.key");
+
+        for (i, (key, _, _)) in MESSAGES.iter().enumerate() {
+            assert!(!MESSAGES[..i].iter().any(|(x, _, _)| x == key), "{}", key);
+        }
+    }
+}
@@ -1,43 +1,450 @@
+pub mod args;
+pub mod channels;
+pub mod database;
+pub mod dispatcher;
+pub mod feature;
 pub mod handler;
+pub mod help;
+pub mod instrumented;
+pub mod interaction;
+pub mod locale;
 pub mod models;
+pub mod permission;
+pub mod prefix;
 
+pub use args::*;
+pub use channels::*;
+pub use database::*;
+pub use dispatcher::*;
+pub use feature::*;
 pub use handler::*;
+pub use help::*;
+pub use instrumented::*;
+pub use interaction::*;
+pub use locale::*;
 pub use models::*;
+pub use permission::*;
+pub use prefix::*;
 
-use diesel::PgConnection;
-use std::sync::{Arc, Mutex};
+use crate::error::{Error, Result};
+use crate::metrics::{EVENTS_RECEIVED, HANDLER_ERRORS, HANDLER_INVOCATIONS};
+use futures::future::join_all;
+use futures::FutureExt;
+use std::any::type_name;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span, Instrument};
 use twilight_gateway::Event;
+use twilight_model::application::interaction::Interaction as DiscordInteraction;
+use twilight_model::gateway::Intents;
+
+type Handlers<T> = Vec<(&'static str, Box<T>)>;
 
 pub struct Bot {
-    message_handlers: Vec<Box<dyn MessageHandler>>,
+    message_handlers: Handlers<dyn MessageHandler>,
+    message_update_handlers: Handlers<dyn MessageUpdateHandler>,
+    message_delete_handlers: Handlers<dyn MessageDeleteHandler>,
+    message_delete_bulk_handlers: Handlers<dyn MessageDeleteBulkHandler>,
+    channel_delete_handlers: Handlers<dyn ChannelDeleteHandler>,
+    timer_handlers: Vec<(&'static str, Duration, Box<dyn TimerHandler>)>,
+    command_handlers: Vec<(&'static str, Arc<dyn CommandHandler>)>,
     context: Context,
 }
 
 impl Bot {
-    pub fn new<T: ResponseCallbacks + 'static>(callbacks: T, db_conn: PgConnection) -> Self {
-        Bot {
+    pub fn new<T: ResponseCallbacks + 'static>(callbacks: T, db: Database) -> Self {
+        let mut bot = Bot {
             message_handlers: Vec::new(),
+            message_update_handlers: Vec::new(),
+            message_delete_handlers: Vec::new(),
+            message_delete_bulk_handlers: Vec::new(),
+            channel_delete_handlers: Vec::new(),
+            timer_handlers: Vec::new(),
+            command_handlers: Vec::new(),
             context: Context {
-                callbacks: Arc::new(callbacks),
-                db_conn: Arc::new(Mutex::new(db_conn)),
+                callbacks: Arc::new(InstrumentedCallbacks(callbacks)),
+                db,
+                prefix: Arc::new(Prefix::new("")),
+                commands: Arc::new(Vec::new()),
+                owners: Arc::new(Vec::new()),
+                current_user_id: None,
+                locales: Arc::new(Locales::new(Locale::default())),
+                features: Arc::new(Features::default()),
+                channels: Arc::new(Channels::default()),
             },
+        };
+        bot.on_command(Help);
+        bot
+    }
+
+    pub fn set_prefix(&mut self, prefix: Prefix) {
+        self.context.prefix = Arc::new(prefix);
+    }
+
+    pub fn set_current_user(&mut self, user_id: u64) {
+        self.context.current_user_id = Some(user_id);
+    }
+
+    pub fn set_locales(&mut self, locales: Locales) {
+        self.context.locales = Arc::new(locales);
+    }
+
+    pub fn set_owners(&mut self, owners: Vec<u64>) {
+        self.context.owners = Arc::new(owners);
+    }
+
+    fn register_feature(&mut self, feature: Option<&'static str>) {
+        if let Some(feature) = feature {
+            Arc::get_mut(&mut self.context.features)
+                .expect("handlers must be registered before dispatching")
+                .register(feature);
+        }
+    }
+
+    pub fn on_message<T: MessageHandler + 'static>(&mut self, handler: T) {
+        self.register_feature(MessageHandler::feature(&handler));
+        self.message_handlers
+            .push((type_name::<T>(), Box::new(handler)))
+    }
+
+    pub fn on_message_update<T: MessageUpdateHandler + 'static>(&mut self, handler: T) {
+        self.message_update_handlers
+            .push((type_name::<T>(), Box::new(handler)))
+    }
+
+    pub fn on_message_delete<T: MessageDeleteHandler + 'static>(&mut self, handler: T) {
+        self.message_delete_handlers
+            .push((type_name::<T>(), Box::new(handler)))
+    }
+
+    pub fn on_message_delete_bulk<T: MessageDeleteBulkHandler + 'static>(&mut self, handler: T) {
+        self.message_delete_bulk_handlers
+            .push((type_name::<T>(), Box::new(handler)))
+    }
+
+    pub fn on_channel_delete<T: ChannelDeleteHandler + 'static>(&mut self, handler: T) {
+        self.channel_delete_handlers
+            .push((type_name::<T>(), Box::new(handler)))
+    }
+
+    pub fn on_timer<T: TimerHandler + 'static>(&mut self, interval: Duration, handler: T) {
+        self.timer_handlers
+            .push((type_name::<T>(), interval, Box::new(handler)))
+    }
+
+    pub fn timers(&self) -> usize {
+        self.timer_handlers.len()
+    }
+
+    pub async fn run_timer(&self, index: usize) {
+        let (name, interval, handler) = &self.timer_handlers[index];
+        let mut ticks = tokio::time::interval(*interval);
+
+        loop {
+            ticks.tick().await;
+            let run = self.run_handler(
+                &self.context,
+                name,
+                None,
+                None,
+                None,
+                handler.on_timer(&self.context),
+            );
+            if AssertUnwindSafe(run).catch_unwind().await.is_err() {
+                error!(handler = name, "timer handler panicked");
+            }
+        }
+    }
+
+    pub fn on_command<T: CommandHandler + 'static>(&mut self, handler: T) {
+        self.register_feature(CommandHandler::feature(&handler));
+        let handler = Arc::new(handler);
+        self.command_handlers
+            .push((type_name::<T>(), handler.clone()));
+        Arc::make_mut(&mut self.context.commands).push(handler.clone());
+        self.message_handlers
+            .push((type_name::<T>(), Box::new(handler)));
+    }
+
+    pub fn slash_commands(&self) -> Vec<SlashCommand> {
+        self.command_handlers
+            .iter()
+            .filter_map(|(_, handler)| handler.slash_command())
+            .collect()
+    }
+
+    pub fn intents(&self) -> Intents {
+        // Channel and thread events keep the channel cache current.
+        Intents::GUILDS | Intents::GUILD_MESSAGES
+    }
+
+    async fn report(
+        &self,
+        context: &Context,
+        handler: &str,
+        guild_id: Option<u64>,
+        channel_id: Option<u64>,
+        message_id: Option<u64>,
+        result: Result<()>,
+    ) {
+        let error = match result {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+
+        if let (true, Some(channel_id)) = (error.is_user_facing(), channel_id) {
+            debug!(%error, "replying with user-facing error");
+            let locale = context.locale(guild_id).await.unwrap_or_default();
+            let reply = Message::to_channel(channel_id, &error.localize(locale));
+            match context.callbacks.send_message(reply).await {
+                Ok(()) => return,
+                Err(error) => error!(%error, "failed to reply with user-facing error"),
+            }
+        }
+
+        error!(handler, ?channel_id, ?message_id, %error, "handler failed");
+    }
+
+    async fn run_handler(
+        &self,
+        context: &Context,
+        name: &str,
+        guild_id: Option<u64>,
+        channel_id: Option<u64>,
+        message_id: Option<u64>,
+        future: impl Future<Output = Result<()>>,
+    ) -> bool {
+        let span = info_span!("handler", handler = name, ?channel_id, ?message_id);
+
+        async move {
+            HANDLER_INVOCATIONS.with_label_values(&[name]).inc();
+            let start = Instant::now();
+            let result = future.await;
+            let succeeded = result.is_ok();
+            if !succeeded {
+                HANDLER_ERRORS.with_label_values(&[name]).inc();
+            }
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            let outcome = if succeeded { "ok" } else { "error" };
+            debug!(elapsed_ms, outcome, "handler finished");
+            self.report(context, name, guild_id, channel_id, message_id, result)
+                .await;
+            succeeded
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn run_handlers<'a, T, F, Fut>(
+        &'a self,
+        handlers: impl IntoIterator<Item = &'a (&'static str, Box<T>)>,
+        guild_id: Option<u64>,
+        channel_id: Option<u64>,
+        message_id: Option<u64>,
+        call: F,
+    ) where
+        T: ?Sized + 'a,
+        F: Fn(&'a T) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        join_all(handlers.into_iter().map(|(name, handler)| {
+            self.run_handler(
+                &self.context,
+                name,
+                guild_id,
+                channel_id,
+                message_id,
+                call(handler),
+            )
+        }))
+        .await;
+    }
+
+    async fn handle_interaction(&self, interaction: Interaction) {
+        let (name, handler) = match self
+            .command_handlers
+            .iter()
+            .find(|(_, handler)| handler.accepts(&interaction.command))
+        {
+            Some(command) => command,
+            None => return,
+        };
+
+        // Permission checks and the handler itself can outlast the 3 seconds
+        // Discord waits for the first response.
+        if let Err(error) = self
+            .context
+            .callbacks
+            .defer_interaction(interaction.id, &interaction.token)
+            .await
+        {
+            error!(%error, "failed to acknowledge interaction");
+            return;
+        }
+
+        let callbacks = Arc::new(InteractionCallbacks::new(
+            Arc::clone(&self.context.callbacks),
+            interaction.clone(),
+        ));
+        let context = Context {
+            callbacks: callbacks.clone(),
+            ..self.context.clone()
+        };
+
+        let content = format!(
+            "/{} {}",
+            interaction.command,
+            interaction.args.as_strs().join(" ")
+        );
+        let message = Message {
+            author: interaction.user,
+            channel_id: interaction.channel_id,
+            content,
+            guild_id: interaction.guild_id,
+            ..Message::default()
+        };
+        let incoming = (interaction.id, message);
+        let (guild_id, channel_id, args) = (
+            interaction.guild_id,
+            interaction.channel_id,
+            &interaction.args,
+        );
+        let command = async {
+            let features = context.features.get(guild_id, &context.db).await?;
+            if !features.enabled(CommandHandler::feature(handler), channel_id) {
+                return Err(Error::UserInput(Text::new("features.command_disabled")));
+            }
+            run_command(handler, args, &incoming, &context).await
+        };
+
+        let succeeded = self
+            .run_handler(
+                &context,
+                name,
+                interaction.guild_id,
+                Some(interaction.channel_id),
+                Some(interaction.id),
+                command,
+            )
+            .await;
+
+        // The deferred response keeps showing "thinking" until a followup
+        // arrives.
+        if !callbacks.responded() {
+            let reply = Text::new(if succeeded {
+                "command.done"
+            } else {
+                "command.failed"
+            });
+            let locale = context
+                .locale(interaction.guild_id)
+                .await
+                .unwrap_or_default();
+            let reply = Message::to_channel(interaction.channel_id, &reply.render(locale));
+            if let Err(error) = callbacks.send_message(reply).await {
+                error!(%error, "failed to respond to interaction");
+            }
         }
     }
 
-    pub fn on_message(&mut self, handler: impl MessageHandler + 'static) {
-        self.message_handlers.push(Box::new(handler))
+    async fn handle_channel_delete(&self, channel: Channel) {
+        self.context.channels.forget(channel.id);
+        let channel = &channel;
+        self.run_handlers(
+            &self.channel_delete_handlers,
+            channel.guild_id,
+            None,
+            None,
+            |handler| handler.on_channel_delete(channel, &self.context),
+        )
+        .await;
     }
 
-    pub async fn handle(&mut self, event: Event) {
+    pub async fn handle(&self, event: Event) {
+        let kind = event.kind();
+        EVENTS_RECEIVED
+            .with_label_values(&[kind.name().unwrap_or("UNKNOWN")])
+            .inc();
+
+        let span = info_span!("event", ?kind);
+        self.handle_event(event).instrument(span).await
+    }
+
+    async fn handle_event(&self, event: Event) {
+        let context = &self.context;
+
         match event {
             Event::MessageCreate(msg) => {
-                let incoming = (msg.id.0.into(), msg.0.into());
-                for handler in self.message_handlers.iter_mut() {
-                    if let Err(error) = handler.on_message(&incoming, &self.context).await {
-                        println!("[ERROR] {}", error);
+                let incoming: &IncomingMessage = &(msg.id.0.into(), msg.0.into());
+                let (message_id, message) = incoming;
+                let features = match context.features.get(message.guild_id, &context.db).await {
+                    Ok(features) => features,
+                    Err(error) => {
+                        error!(%error, "failed to load feature toggles");
+                        Arc::default()
                     }
+                };
+                let handlers = self.message_handlers.iter().filter(|(_, handler)| {
+                    handler
+                        .author_filter()
+                        .accepts(&message.author, context.current_user_id)
+                        && features.enabled(handler.feature(), message.channel_id)
+                });
+                self.run_handlers(
+                    handlers,
+                    message.guild_id,
+                    Some(message.channel_id),
+                    Some(*message_id),
+                    |handler| handler.on_message(incoming, context),
+                )
+                .await;
+            }
+            Event::MessageUpdate(update) => {
+                let update: &MessageUpdate = &(*update).into();
+                self.run_handlers(
+                    &self.message_update_handlers,
+                    None,
+                    Some(update.channel_id),
+                    Some(update.id),
+                    |handler| handler.on_message_update(update, context),
+                )
+                .await;
+            }
+            Event::MessageDelete(delete) => {
+                let delete: &MessageDelete = &delete.into();
+                self.run_handlers(
+                    &self.message_delete_handlers,
+                    None,
+                    Some(delete.channel_id),
+                    Some(delete.id),
+                    |handler| handler.on_message_delete(delete, context),
+                )
+                .await;
+            }
+            Event::MessageDeleteBulk(delete) => {
+                let delete: &MessageDeleteBulk = &delete.into();
+                self.run_handlers(
+                    &self.message_delete_bulk_handlers,
+                    None,
+                    Some(delete.channel_id),
+                    None,
+                    |handler| handler.on_message_delete_bulk(delete, context),
+                )
+                .await;
+            }
+            Event::InteractionCreate(interaction) => {
+                if let DiscordInteraction::ApplicationCommand(command) = interaction.0 {
+                    self.handle_interaction((*command).into()).await;
                 }
             }
+            Event::ChannelCreate(channel) => context.channels.update(channel.0.into()),
+            Event::ChannelUpdate(channel) => context.channels.update(channel.0.into()),
+            Event::ThreadCreate(thread) => context.channels.update(thread.0.into()),
+            Event::ThreadUpdate(thread) => context.channels.update(thread.0.into()),
+            Event::ChannelDelete(channel) => self.handle_channel_delete(channel.0.into()).await,
+            Event::ThreadDelete(thread) => self.handle_channel_delete(thread.0.into()).await,
             _ => (),
         }
     }
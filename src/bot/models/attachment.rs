@@ -4,6 +4,19 @@ use twilight_model::channel::Attachment as DiscordAttachment;
 pub struct Attachment {
     pub filename: String,
     pub url: String,
+    pub content_type: Option<String>,
+}
+
+impl Attachment {
+    pub fn is_image(&self) -> bool {
+        self.content_type
+            .as_deref()
+            .is_some_and(|x| x.starts_with("image/"))
+    }
+
+    pub fn is_spoiler(&self) -> bool {
+        self.filename.starts_with("SPOILER_")
+    }
 }
 
 impl From<DiscordAttachment> for Attachment {
@@ -11,6 +24,7 @@ impl From<DiscordAttachment> for Attachment {
         Attachment {
             filename: attachment.filename,
             url: attachment.url,
+            content_type: attachment.content_type,
         }
     }
 }
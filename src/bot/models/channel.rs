@@ -1,16 +1,115 @@
-use twilight_model::channel::Channel as DiscordChannel;
+use serde_json::Value;
+use twilight_model::channel::{Channel as DiscordChannel, GuildChannel};
+
+// Discord's type for forum channels, which twilight doesn't know yet.
+const GUILD_FORUM: u64 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelKind {
+    Text,
+    Voice,
+    Category,
+    // Holds only threads, its posts.
+    Forum,
+    Thread,
+    Private,
+}
 
 #[derive(Clone)]
 pub struct Channel {
     pub id: u64,
     pub name: String,
+    pub kind: ChannelKind,
+    // The category of a guild channel, or the channel a thread was started in.
+    pub parent_id: Option<u64>,
+    pub guild_id: Option<u64>,
 }
 
 impl From<DiscordChannel> for Channel {
     fn from(channel: DiscordChannel) -> Self {
+        let (kind, parent_id, guild_id) = match &channel {
+            DiscordChannel::Guild(guild) => {
+                let (kind, parent_id) = match guild {
+                    GuildChannel::Category(_) => (ChannelKind::Category, None),
+                    GuildChannel::Text(x) => (ChannelKind::Text, x.parent_id),
+                    GuildChannel::Voice(x) | GuildChannel::Stage(x) => {
+                        (ChannelKind::Voice, x.parent_id)
+                    }
+                    GuildChannel::NewsThread(x) => (ChannelKind::Thread, x.parent_id),
+                    GuildChannel::PublicThread(x) => (ChannelKind::Thread, x.parent_id),
+                    GuildChannel::PrivateThread(x) => (ChannelKind::Thread, x.parent_id),
+                };
+                (kind, parent_id, guild.guild_id())
+            }
+            _ => (ChannelKind::Private, None, None),
+        };
+
         Channel {
             id: channel.id().0.into(),
             name: channel.name().unwrap_or("").to_owned(),
+            kind,
+            parent_id: parent_id.map(|x| x.0.into()),
+            guild_id: guild_id.map(|x| x.0.into()),
+        }
+    }
+}
+
+impl Channel {
+    // Reads a channel as the API returns it, including the kinds twilight
+    // can't parse.
+    pub fn from_json(value: Value) -> Option<Self> {
+        if value["type"].as_u64() != Some(GUILD_FORUM) {
+            return serde_json::from_value::<DiscordChannel>(value)
+                .ok()
+                .map(Channel::from);
         }
+
+        let id = |key: &str| value[key].as_str().and_then(|x| x.parse().ok());
+        Some(Channel {
+            id: id("id")?,
+            name: value["name"].as_str().unwrap_or("").to_owned(),
+            kind: ChannelKind::Forum,
+            parent_id: id("parent_id"),
+            guild_id: id("guild_id"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_from_json() {
+        use super::{Channel, ChannelKind};
+        use serde_json::json;
+        let forum = Channel::from_json(json!({
+            "id": "10",
+            "type": 15,
+            "name": "forum",
+            "parent_id": "20",
+            "guild_id": "30",
+        }))
+        .unwrap();
+        assert_eq!(forum.id, 10);
+        assert_eq!(forum.kind, ChannelKind::Forum);
+        assert_eq!(forum.parent_id, Some(20));
+        assert_eq!(forum.guild_id, Some(30));
+
+        let thread = Channel::from_json(json!({
+            "id": "11",
+            "type": 11,
+            "name": "post",
+            "parent_id": "10",
+            "guild_id": "30",
+            "thread_metadata": {
+                "archived": false,
+                "auto_archive_duration": 1440,
+                "archive_timestamp": "2022-01-01T00:00:00+00:00",
+            },
+        }))
+        .unwrap();
+        assert_eq!(thread.kind, ChannelKind::Thread);
+        assert_eq!(thread.parent_id, Some(10));
+
+        assert!(Channel::from_json(json!({ "id": "12", "type": 99 })).is_none());
     }
 }
@@ -0,0 +1,245 @@
+use crate::bot::{Locale, Text};
+use serde_json::{Map, Value};
+use twilight_model::application::command::{
+    BaseCommandOptionData, ChannelCommandOptionData, ChoiceCommandOptionData, Command,
+    CommandOption as DiscordCommandOption, CommandType, NumberCommandOptionData,
+    OptionsCommandOptionData,
+};
+use twilight_model::id::CommandVersionId;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommandOptionKind {
+    String,
+    Integer,
+    Boolean,
+    User,
+    Channel,
+    Role,
+}
+
+#[derive(Clone, Debug)]
+pub enum CommandOption {
+    // Descriptions are catalog keys.
+    SubCommand {
+        name: String,
+        description: &'static str,
+        options: Vec<CommandOption>,
+    },
+    Value {
+        name: String,
+        description: &'static str,
+        kind: CommandOptionKind,
+        required: bool,
+    },
+}
+
+impl CommandOption {
+    pub fn sub_command(name: &str, description: &'static str, options: Vec<CommandOption>) -> Self {
+        CommandOption::SubCommand {
+            name: name.to_string(),
+            description,
+            options,
+        }
+    }
+
+    pub fn value(
+        name: &str,
+        description: &'static str,
+        kind: CommandOptionKind,
+        required: bool,
+    ) -> Self {
+        CommandOption::Value {
+            name: name.to_string(),
+            description,
+            kind,
+            required,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            CommandOption::SubCommand { description, .. }
+            | CommandOption::Value { description, .. } => description,
+        }
+    }
+
+    fn options(&self) -> &[CommandOption] {
+        match self {
+            CommandOption::SubCommand { options, .. } => options,
+            CommandOption::Value { .. } => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SlashCommand {
+    pub name: String,
+    // A catalog key, like the option descriptions.
+    pub description: &'static str,
+    pub options: Vec<CommandOption>,
+}
+
+impl SlashCommand {
+    pub fn new(name: &str, description: &'static str, options: Vec<CommandOption>) -> Self {
+        SlashCommand {
+            name: name.to_string(),
+            description,
+            options,
+        }
+    }
+
+    // The command as registered with Discord: described in the default
+    // locale, with every catalog language as a localization. twilight's
+    // `Command` has no field for those, so they are added to its JSON.
+    pub fn to_json(&self) -> Value {
+        let mut value =
+            serde_json::to_value(Command::from(self.clone())).expect("commands always serialize");
+        localize(&mut value, self.description, &self.options);
+        value
+    }
+}
+
+// Discord's codes for each catalog language.
+fn discord_locales(locale: Locale) -> &'static [&'static str] {
+    match locale {
+        Locale::Ja => &["ja"],
+        Locale::En => &["en-US", "en-GB"],
+    }
+}
+
+fn localize(value: &mut Value, description: &'static str, options: &[CommandOption]) {
+    let mut localizations = Map::new();
+    for locale in Locale::ALL {
+        let text = Text::new(description).render(*locale);
+        for code in discord_locales(*locale) {
+            localizations.insert(code.to_string(), Value::String(text.clone()));
+        }
+    }
+    value["description_localizations"] = Value::Object(localizations);
+
+    // Options serialize in the order they were declared.
+    if let Some(values) = value.get_mut("options").and_then(Value::as_array_mut) {
+        for (value, option) in values.iter_mut().zip(options) {
+            localize(value, option.description(), option.options());
+        }
+    }
+}
+
+impl From<CommandOption> for DiscordCommandOption {
+    fn from(option: CommandOption) -> Self {
+        let (name, description, kind, required) = match option {
+            CommandOption::SubCommand {
+                name,
+                description,
+                options,
+            } => {
+                return DiscordCommandOption::SubCommand(OptionsCommandOptionData {
+                    description: Text::new(description).render(Locale::default()),
+                    name,
+                    options: options.into_iter().map(Into::into).collect(),
+                })
+            }
+            CommandOption::Value {
+                name,
+                description,
+                kind,
+                required,
+            } => (
+                name,
+                Text::new(description).render(Locale::default()),
+                kind,
+                required,
+            ),
+        };
+
+        let base = BaseCommandOptionData {
+            description: description.clone(),
+            name: name.clone(),
+            required,
+        };
+
+        match kind {
+            CommandOptionKind::String => DiscordCommandOption::String(ChoiceCommandOptionData {
+                autocomplete: false,
+                choices: Vec::new(),
+                description,
+                name,
+                required,
+            }),
+            CommandOptionKind::Integer => DiscordCommandOption::Integer(NumberCommandOptionData {
+                autocomplete: false,
+                choices: Vec::new(),
+                description,
+                max_value: None,
+                min_value: None,
+                name,
+                required,
+            }),
+            CommandOptionKind::Boolean => DiscordCommandOption::Boolean(base),
+            CommandOptionKind::User => DiscordCommandOption::User(base),
+            CommandOptionKind::Channel => DiscordCommandOption::Channel(ChannelCommandOptionData {
+                channel_types: Vec::new(),
+                description,
+                name,
+                required,
+            }),
+            CommandOptionKind::Role => DiscordCommandOption::Role(base),
+        }
+    }
+}
+
+impl From<SlashCommand> for Command {
+    fn from(command: SlashCommand) -> Self {
+        Command {
+            application_id: None,
+            default_permission: None,
+            description: Text::new(command.description).render(Locale::default()),
+            guild_id: None,
+            id: None,
+            kind: CommandType::ChatInput,
+            name: command.name,
+            options: command.options.into_iter().map(Into::into).collect(),
+            version: CommandVersionId::new(1).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_to_json() {
+        use super::{CommandOption, CommandOptionKind, SlashCommand};
+        let command = SlashCommand::new(
+            "prefix",
+            "prefix.description",
+            vec![CommandOption::sub_command(
+                "set",
+                "prefix.slash.set",
+                vec![CommandOption::value(
+                    "prefix",
+                    "prefix.slash.prefix",
+                    CommandOptionKind::String,
+                    true,
+                )],
+            )],
+        );
+        let json = command.to_json();
+        assert_eq!(
+            json["description"],
+            "このサーバーのコマンドプレフィックスを設定します"
+        );
+        assert_eq!(
+            json["description_localizations"]["en-US"],
+            "Sets the command prefix for this server"
+        );
+        let set = &json["options"][0];
+        assert_eq!(
+            set["description_localizations"]["ja"],
+            "プレフィックスを変更します"
+        );
+        assert_eq!(
+            set["options"][0]["description_localizations"]["en-GB"],
+            "The new prefix"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use twilight_model::channel::embed::{Embed as DiscordEmbed, EmbedAuthor, EmbedFooter, EmbedImage};
+use twilight_model::datetime::Timestamp;
 
 #[derive(Clone, Default, Debug)]
 pub struct Embed {
@@ -7,6 +8,12 @@ pub struct Embed {
     pub description: Option<String>,
     pub footer: Option<String>,
     pub image: Option<String>,
+    // Also links the author name. Discord shows the images of consecutive
+    // embeds sharing a url as one gallery.
+    pub url: Option<String>,
+    pub color: Option<u32>,
+    // Unix seconds.
+    pub timestamp: Option<i64>,
 }
 
 impl Embed {
@@ -38,6 +45,21 @@ impl Embed {
         self.image = Some(image);
         self
     }
+
+    pub fn url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    pub fn color(mut self, color: u32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
 }
 
 impl From<DiscordEmbed> for Embed {
@@ -51,6 +73,9 @@ impl From<DiscordEmbed> for Embed {
             description: embed.description,
             footer: embed.footer.map(|x| x.text),
             image: embed.image.map(|x| x.url),
+            url: embed.url,
+            color: embed.color,
+            timestamp: embed.timestamp.map(|x| x.as_secs()),
         }
     }
 }
@@ -63,17 +88,19 @@ impl From<Embed> for DiscordEmbed {
             description,
             footer,
             image,
-            ..
+            url,
+            color,
+            timestamp,
         } = embed;
         DiscordEmbed {
             author: author_name.map(|author_name| EmbedAuthor {
                 icon_url: avatar_url,
                 name: author_name,
                 proxy_icon_url: None,
-                url: None,
+                url: url.clone(),
             }),
-            color: None,
-            description: description,
+            color,
+            description,
             fields: Vec::new(),
             footer: footer.map(|footer| EmbedFooter {
                 icon_url: None,
@@ -89,9 +116,9 @@ impl From<Embed> for DiscordEmbed {
             kind: "".to_string(),
             provider: None,
             thumbnail: None,
-            timestamp: None,
+            timestamp: timestamp.and_then(|x| Timestamp::from_secs(x).ok()),
             title: None,
-            url: None,
+            url,
             video: None,
         }
     }
@@ -0,0 +1,64 @@
+use super::User;
+use crate::bot::Args;
+use twilight_model::application::interaction::application_command::{
+    ApplicationCommand, CommandDataOption, CommandOptionValue,
+};
+
+#[derive(Clone)]
+pub struct Interaction {
+    pub id: u64,
+    pub token: String,
+    pub channel_id: u64,
+    pub guild_id: Option<u64>,
+    pub user: User,
+    pub command: String,
+    pub args: Args,
+}
+
+// Options are flattened into the same argument list a text command would
+// receive, so that a `CommandHandler` can serve both paths. Tokens are tagged
+// the way the tokenizer tags them: only boolean options become `--flags`.
+fn flatten_options(options: Vec<CommandDataOption>, args: &mut Vec<(String, bool)>) {
+    for option in options {
+        match option.value {
+            CommandOptionValue::SubCommand(options)
+            | CommandOptionValue::SubCommandGroup(options) => {
+                args.push((option.name, false));
+                flatten_options(options, args);
+            }
+            CommandOptionValue::Boolean(true) => args.push((format!("--{}", option.name), true)),
+            CommandOptionValue::Boolean(false) => {}
+            CommandOptionValue::Channel(id) => args.push((format!("<#{}>", id), false)),
+            CommandOptionValue::Integer(value) => args.push((value.to_string(), false)),
+            CommandOptionValue::Mentionable(id) => args.push((id.to_string(), false)),
+            CommandOptionValue::Number(value) => args.push((value.0.to_string(), false)),
+            CommandOptionValue::Role(id) => args.push((format!("<@&{}>", id), false)),
+            CommandOptionValue::String(value) => args.push((value, false)),
+            CommandOptionValue::User(id) => args.push((format!("<@{}>", id), false)),
+        }
+    }
+}
+
+impl From<ApplicationCommand> for Interaction {
+    fn from(command: ApplicationCommand) -> Self {
+        let user = command
+            .member
+            .and_then(|member| member.user)
+            .or(command.user)
+            .map(Into::into)
+            .unwrap_or_default();
+
+        let mut args = Vec::new();
+        flatten_options(command.data.options, &mut args);
+
+        Interaction {
+            id: command.id.0.into(),
+            token: command.token,
+            channel_id: command.channel_id.0.into(),
+            guild_id: command.guild_id.map(|x| x.0.into()),
+            user,
+            command: command.data.name,
+            args: Args::from_tokens(args),
+        }
+    }
+}
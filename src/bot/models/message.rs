@@ -1,16 +1,35 @@
 use super::{Attachment, Embed, User};
 use twilight_model::channel::Message as DiscordMessage;
+use twilight_model::gateway::payload::incoming::{
+    MessageDelete as DiscordMessageDelete, MessageDeleteBulk as DiscordMessageDeleteBulk,
+    MessageUpdate as DiscordMessageUpdate,
+};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Message {
     pub attachments: Vec<Attachment>,
     pub author: User,
+    pub author_roles: Vec<u64>,
     pub channel_id: u64,
     pub content: String,
     pub embeds: Vec<Embed>,
+    pub files: Vec<(String, Vec<u8>)>,
+    pub guild_id: Option<u64>,
+    pub pinned: bool,
+    pub reply_to: Option<Box<IncomingMessage>>,
+    // Unix seconds; 0 for messages the bot builds itself.
+    pub timestamp: i64,
 }
 
 impl Message {
+    pub fn to_channel(channel_id: u64, content: &str) -> Self {
+        Self {
+            channel_id,
+            content: content.to_string(),
+            ..Self::default()
+        }
+    }
+
     pub fn reply(&self, content: &str) -> Self {
         Self {
             content: content.to_string(),
@@ -22,18 +41,86 @@ impl Message {
         self.embeds = embeds;
         self
     }
+
+    pub fn add_file(mut self, filename: &str, data: Vec<u8>) -> Self {
+        self.files.push((filename.to_string(), data));
+        self
+    }
 }
 
 impl From<DiscordMessage> for Message {
     fn from(message: DiscordMessage) -> Self {
+        let author = User {
+            webhook: message.webhook_id.is_some(),
+            ..message.author.into()
+        };
+
         Message {
             attachments: message.attachments.into_iter().map(Into::into).collect(),
-            author: message.author.into(),
+            author,
+            author_roles: message
+                .member
+                .map(|x| x.roles.into_iter().map(|x| x.0.into()).collect())
+                .unwrap_or_default(),
             channel_id: message.channel_id.0.into(),
             content: message.content,
             embeds: message.embeds.into_iter().map(Into::into).collect(),
+            files: Vec::new(),
+            guild_id: message.guild_id.map(|x| x.0.into()),
+            pinned: message.pinned,
+            reply_to: message
+                .referenced_message
+                .map(|x| Box::new((x.id.0.into(), (*x).into()))),
+            timestamp: message.timestamp.as_secs(),
         }
     }
 }
 
 pub type IncomingMessage = (u64, Message);
+
+#[derive(Clone)]
+pub struct MessageUpdate {
+    pub id: u64,
+    pub channel_id: u64,
+    pub pinned: Option<bool>,
+}
+
+impl From<DiscordMessageUpdate> for MessageUpdate {
+    fn from(update: DiscordMessageUpdate) -> Self {
+        MessageUpdate {
+            id: update.id.0.into(),
+            channel_id: update.channel_id.0.into(),
+            pinned: update.pinned,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MessageDelete {
+    pub id: u64,
+    pub channel_id: u64,
+}
+
+impl From<DiscordMessageDelete> for MessageDelete {
+    fn from(delete: DiscordMessageDelete) -> Self {
+        MessageDelete {
+            id: delete.id.0.into(),
+            channel_id: delete.channel_id.0.into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MessageDeleteBulk {
+    pub ids: Vec<u64>,
+    pub channel_id: u64,
+}
+
+impl From<DiscordMessageDeleteBulk> for MessageDeleteBulk {
+    fn from(delete: DiscordMessageDeleteBulk) -> Self {
+        MessageDeleteBulk {
+            ids: delete.ids.into_iter().map(|x| x.0.into()).collect(),
+            channel_id: delete.channel_id.0.into(),
+        }
+    }
+}
@@ -1,11 +1,15 @@
 mod attachment;
 mod channel;
+mod command;
 mod embed;
+mod interaction;
 mod message;
 mod user;
 
 pub use attachment::*;
 pub use channel::*;
+pub use command::*;
 pub use embed::*;
+pub use interaction::*;
 pub use message::*;
 pub use user::*;
@@ -1,10 +1,12 @@
 use twilight_model::user::User as DiscordUser;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct User {
     pub id: u64,
     pub name: String,
     pub avatar: Option<String>,
+    pub bot: bool,
+    pub webhook: bool,
 }
 
 impl From<DiscordUser> for User {
@@ -13,6 +15,8 @@ impl From<DiscordUser> for User {
             id: user.id.0.into(),
             name: user.name,
             avatar: user.avatar,
+            bot: user.bot,
+            webhook: false,
         }
     }
 }
@@ -0,0 +1,79 @@
+use super::{Context, Locale, Message, Text};
+use crate::error::{Error, Result};
+use twilight_model::guild::Permissions;
+
+fn permission_names(permissions: Permissions, locale: Locale) -> String {
+    let names = [
+        (Permissions::ADMINISTRATOR, "permission.administrator"),
+        (Permissions::MANAGE_GUILD, "permission.manage_guild"),
+        (Permissions::MANAGE_CHANNELS, "permission.manage_channels"),
+        (Permissions::MANAGE_MESSAGES, "permission.manage_messages"),
+        (Permissions::MANAGE_ROLES, "permission.manage_roles"),
+    ];
+
+    let mut rest = permissions;
+    let mut result: Vec<String> = Vec::new();
+    for (permission, name) in names {
+        if rest.contains(permission) {
+            result.push(Text::new(name).render(locale));
+            rest.remove(permission);
+        }
+    }
+    if !rest.is_empty() {
+        result.push(format!("{:?}", rest));
+    }
+
+    result.join(&Text::new("permission.separator").render(locale))
+}
+
+// Bot owners bypass the check so they can fix configuration anywhere.
+pub async fn check_permissions(
+    required: Permissions,
+    message: &Message,
+    context: &Context,
+) -> Result<()> {
+    if required.is_empty() || context.owners.contains(&message.author.id) {
+        return Ok(());
+    }
+
+    let guild_id = message
+        .guild_id
+        .ok_or_else(|| Error::Permission(Text::new("command.guild_only")))?;
+
+    let granted = context
+        .callbacks
+        .fetch_permissions(guild_id, message.author.id)
+        .await
+        .unwrap_or_else(Permissions::empty);
+
+    if granted.contains(Permissions::ADMINISTRATOR) || granted.contains(required) {
+        return Ok(());
+    }
+
+    let locale = context.locale(Some(guild_id)).await?;
+    Err(Error::Permission(Text::new("permission.missing").param(
+        "permissions",
+        permission_names(required - granted, locale),
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_permission_names() {
+        use super::{permission_names, Locale, Permissions};
+        let permissions = Permissions::MANAGE_MESSAGES | Permissions::MANAGE_CHANNELS;
+        assert_eq!(
+            permission_names(permissions, Locale::Ja),
+            "チャンネルの管理、メッセージの管理"
+        );
+        assert_eq!(
+            permission_names(permissions, Locale::En),
+            "Manage Channels, Manage Messages"
+        );
+        assert_eq!(
+            permission_names(Permissions::KICK_MEMBERS, Locale::Ja),
+            "KICK_MEMBERS"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use super::Database;
+use crate::error::Result;
+use crate::schema::guild_config::dsl as guild_config;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub struct Prefix {
+    default: String,
+    mention: Option<u64>,
+    guilds: Mutex<HashMap<u64, Option<String>>>,
+}
+
+fn strip_mention(content: &str, user_id: u64) -> Option<&str> {
+    let rest = content.strip_prefix("<@")?;
+    let rest = rest.strip_prefix('!').unwrap_or(rest);
+    let rest = rest.strip_prefix(&user_id.to_string())?;
+    rest.strip_prefix('>')
+}
+
+fn strip_prefix<'a>(content: &'a str, prefix: &str, mention: Option<u64>) -> Option<&'a str> {
+    let content = content.trim_start();
+
+    if let Some(rest) = mention.and_then(|user_id| strip_mention(content, user_id)) {
+        return Some(rest.trim_start());
+    }
+
+    content.strip_prefix(prefix)
+}
+
+impl Prefix {
+    pub fn new(default: &str) -> Self {
+        Prefix {
+            default: default.to_string(),
+            mention: None,
+            guilds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn mention(mut self, user_id: u64) -> Self {
+        self.mention = Some(user_id);
+        self
+    }
+
+    pub fn forget(&self, guild_id: u64) {
+        self.guilds.lock().unwrap().remove(&guild_id);
+    }
+
+    async fn guild_prefix(&self, guild_id: u64, db: &Database) -> Result<Option<String>> {
+        if let Some(prefix) = self.guilds.lock().unwrap().get(&guild_id) {
+            return Ok(prefix.clone());
+        }
+
+        let prefix = db
+            .run(move |conn| {
+                guild_config::guild_config
+                    .select(guild_config::prefix)
+                    .find(guild_id as i64)
+                    .first::<Option<String>>(conn)
+                    .optional()
+            })
+            .await?
+            .flatten();
+
+        self.guilds.lock().unwrap().insert(guild_id, prefix.clone());
+
+        Ok(prefix)
+    }
+
+    pub async fn get(&self, guild_id: Option<u64>, db: &Database) -> Result<String> {
+        let prefix = match guild_id {
+            Some(guild_id) => self.guild_prefix(guild_id, db).await?,
+            None => None,
+        };
+
+        Ok(prefix.unwrap_or_else(|| self.default.clone()))
+    }
+
+    pub async fn strip<'a>(
+        &self,
+        content: &'a str,
+        guild_id: Option<u64>,
+        db: &Database,
+    ) -> Result<Option<&'a str>> {
+        let prefix = self.get(guild_id, db).await?;
+
+        Ok(strip_prefix(content, &prefix, self.mention))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_strip_prefix() {
+        use super::strip_prefix;
+        assert_eq!(strip_prefix("!meslimit", "!", None), Some("meslimit"));
+        assert_eq!(strip_prefix(" !meslimit", "!", None), Some("meslimit"));
+        assert_eq!(strip_prefix("meslimit", "!", None), None);
+        assert_eq!(strip_prefix("meslimit", "", None), Some("meslimit"));
+        assert_eq!(
+            strip_prefix("<@42> meslimit", "!", Some(42)),
+            Some("meslimit")
+        );
+        assert_eq!(
+            strip_prefix("<@!42> meslimit", "!", Some(42)),
+            Some("meslimit")
+        );
+        assert_eq!(strip_prefix("<@43> meslimit", "!", Some(42)), None);
+    }
+}
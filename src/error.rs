@@ -0,0 +1,109 @@
+use crate::bot::{Locale, Text};
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use twilight_http::request::application::InteractionError;
+use twilight_http::request::channel::message::create_message::CreateMessageError;
+use twilight_http::request::channel::message::get_channel_messages::GetChannelMessagesError;
+use twilight_http::request::channel::message::get_channel_messages_configured::GetChannelMessagesConfiguredError;
+use twilight_http::response::DeserializeBodyError;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(BoxError),
+    Database(BoxError),
+    UserInput(Text),
+    // The arguments did not match any form the command accepts; the caller
+    // replies with the command's usage.
+    Usage,
+    Permission(Text),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub fn is_user_facing(&self) -> bool {
+        matches!(
+            self,
+            Error::UserInput(_) | Error::Usage | Error::Permission(_)
+        )
+    }
+
+    pub fn localize(&self, locale: Locale) -> String {
+        match self {
+            Error::UserInput(text) | Error::Permission(text) => text.render(locale),
+            Error::Usage => Text::new("command.invalid").render(locale),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(error) => write!(f, "Discord HTTP error: {}", error),
+            Error::Database(error) => write!(f, "database error: {}", error),
+            Error::UserInput(_) | Error::Usage | Error::Permission(_) => {
+                f.write_str(&self.localize(Locale::default()))
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Http(error) | Error::Database(error) => Some(error.as_ref()),
+            Error::UserInput(_) | Error::Usage | Error::Permission(_) => None,
+        }
+    }
+}
+
+impl From<twilight_http::Error> for Error {
+    fn from(error: twilight_http::Error) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<DeserializeBodyError> for Error {
+    fn from(error: DeserializeBodyError) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<CreateMessageError> for Error {
+    fn from(error: CreateMessageError) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<GetChannelMessagesError> for Error {
+    fn from(error: GetChannelMessagesError) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<GetChannelMessagesConfiguredError> for Error {
+    fn from(error: GetChannelMessagesConfiguredError) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<InteractionError> for Error {
+    fn from(error: InteractionError) -> Self {
+        Error::Http(error.into())
+    }
+}
+
+impl From<diesel::result::Error> for Error {
+    fn from(error: diesel::result::Error) -> Self {
+        Error::Database(error.into())
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for Error {
+    fn from(error: diesel::r2d2::PoolError) -> Self {
+        Error::Database(error.into())
+    }
+}
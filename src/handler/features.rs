@@ -0,0 +1,177 @@
+use crate::bot::{
+    Args, CommandHandler, CommandOption, CommandOptionKind, Context, FeatureScope, IncomingMessage,
+    SlashCommand, Text,
+};
+use crate::error::{Error, Result};
+use crate::models::FeatureToggle;
+use crate::schema::feature_toggle::dsl as feature_toggle;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use twilight_model::guild::Permissions;
+
+pub struct FeatureConfigurator;
+
+fn feature_options() -> Vec<CommandOption> {
+    vec![
+        CommandOption::value(
+            "feature",
+            "features.slash.feature",
+            CommandOptionKind::String,
+            true,
+        ),
+        CommandOption::value(
+            "channel",
+            "features.slash.channel",
+            CommandOptionKind::Boolean,
+            false,
+        ),
+    ]
+}
+
+#[async_trait]
+impl CommandHandler for FeatureConfigurator {
+    fn name(&self) -> &'static str {
+        "features"
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        &[
+            "",
+            "enable <feature> [--channel]",
+            "disable <feature> [--channel]",
+            "reset <feature> [--channel]",
+        ]
+    }
+
+    fn description(&self) -> &'static str {
+        "features.description"
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        Some(SlashCommand::new(
+            self.name(),
+            self.description(),
+            vec![
+                CommandOption::sub_command("list", "features.slash.list", vec![]),
+                CommandOption::sub_command("enable", "features.slash.enable", feature_options()),
+                CommandOption::sub_command("disable", "features.slash.disable", feature_options()),
+                CommandOption::sub_command("reset", "features.slash.reset", feature_options()),
+            ],
+        ))
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
+        (_, message): &IncomingMessage,
+        context: &Context,
+    ) -> Result<()> {
+        let guild_id = message
+            .guild_id
+            .ok_or_else(|| Error::UserInput(Text::new("command.guild_only")))?;
+        let locale = context.locale(message.guild_id).await?;
+
+        let (name, enabled) = match args.as_strs()[..] {
+            [] | ["list"] => {
+                let features = context.features.get(message.guild_id, &context.db).await?;
+                let mut reply = Text::new("features.list").render(locale);
+                for name in context.features.names() {
+                    let (enabled, scope) = features.state(name, message.channel_id);
+                    let state = if enabled {
+                        "features.on"
+                    } else {
+                        "features.off"
+                    };
+                    let scope = match scope {
+                        FeatureScope::Default => "features.scope.default",
+                        FeatureScope::Guild => "features.scope.guild",
+                        FeatureScope::Channel => "features.scope.channel",
+                    };
+                    reply += &format!(
+                        "\n`{}` — {} ({})",
+                        name,
+                        Text::new(state).render(locale),
+                        Text::new(scope).render(locale)
+                    );
+                }
+                return context.callbacks.send_message(message.reply(&reply)).await;
+            }
+            ["enable", name] => (name, Some(true)),
+            ["disable", name] => (name, Some(false)),
+            ["reset", name] => (name, None),
+            _ => return Err(Error::Usage),
+        };
+
+        let name = context
+            .features
+            .names()
+            .iter()
+            .copied()
+            .find(|x| *x == name)
+            .ok_or_else(|| {
+                Error::UserInput(
+                    Text::new("features.unknown")
+                        .param("name", name)
+                        .param("features", context.features.names().join(", ")),
+                )
+            })?;
+        let channel_id = if args.flag("channel") {
+            message.channel_id as i64
+        } else {
+            0
+        };
+
+        context
+            .db
+            .run(move |conn| {
+                let existing = feature_toggle::feature_toggle
+                    .filter(feature_toggle::guild_id.eq(guild_id as i64))
+                    .filter(feature_toggle::channel_id.eq(channel_id))
+                    .filter(feature_toggle::feature.eq(name));
+
+                match enabled {
+                    Some(enabled) => diesel::insert_into(feature_toggle::feature_toggle)
+                        .values(FeatureToggle {
+                            guild_id: guild_id as i64,
+                            channel_id,
+                            feature: name.to_string(),
+                            enabled,
+                        })
+                        .on_conflict((
+                            feature_toggle::guild_id,
+                            feature_toggle::channel_id,
+                            feature_toggle::feature,
+                        ))
+                        .do_update()
+                        .set(feature_toggle::enabled.eq(enabled))
+                        .execute(conn),
+                    None => diesel::delete(existing).execute(conn),
+                }
+            })
+            .await?;
+
+        context.features.forget(guild_id);
+
+        let reply = match enabled {
+            Some(enabled) => {
+                let state = if enabled {
+                    "features.on"
+                } else {
+                    "features.off"
+                };
+                Text::new("features.updated")
+                    .param("name", name)
+                    .param("state", Text::new(state).render(locale))
+            }
+            None => Text::new("features.reset").param("name", name),
+        };
+        context
+            .callbacks
+            .send_message(message.reply(&reply.render(locale)))
+            .await
+    }
+}
@@ -1,115 +1,1925 @@
-use crate::bot::{CommandHandler, Context, IncomingMessage, MessageHandler};
-use crate::models::{History, HistoryWindowConfig};
-use crate::schema::{history::dsl as history, history_window_config::dsl as history_window_config};
+use crate::bot::{
+    format_duration, Args, Channel, ChannelDeleteHandler, ChannelKind, CommandHandler,
+    CommandOption, CommandOptionKind, Context, Database, IncomingMessage, Message, MessageDelete,
+    MessageDeleteBulk, MessageDeleteBulkHandler, MessageDeleteHandler, MessageHandler,
+    MessageUpdate, MessageUpdateHandler, SlashCommand, Text, TimerHandler,
+};
+use crate::error::{Error, Result};
+use crate::metrics::{HISTORY_WINDOW_DEAD_LETTERED, HISTORY_WINDOW_DELETED};
+use crate::models::{ArchivedMessage, History, HistoryWindowConfig, PendingDeletion};
+use crate::schema::history::BoxedQuery as HistoryQuery;
+use crate::schema::{
+    deletion_queue::dsl as deletion_queue, history::dsl as history,
+    history_window_config::dsl as history_window_config, message_archive::dsl as message_archive,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::dsl::not;
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use std::error::Error;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info_span, warn, Instrument};
+use twilight_model::guild::Permissions;
 
 pub struct HistoryWindow;
 
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+// Discord refuses to bulk delete messages older than two weeks; the margin
+// covers the time between picking a batch and sending it.
+const BULK_DELETE_MAX_AGE: Duration = Duration::from_secs((14 * 24 - 1) * 60 * 60);
+const BULK_DELETE_MAX_COUNT: usize = 100;
+// Seconds a claimed deletion stays invisible to other sweeps.
+const DELETE_LEASE: i64 = 5 * 60;
+// Deleting one by one runs at about one message a second under rate limits.
+const DELETE_CLAIM_LIMIT: i64 = 200;
+const DELETE_RETRY_BASE: i64 = 60;
+const DELETE_RETRY_MAX: i64 = 24 * 60 * 60;
+const DELETE_MAX_ATTEMPTS: i32 = 8;
+const BACKFILL_PAGE_SIZE: u64 = 100;
+const BACKFILL_REPORT_INTERVAL: usize = 1000;
+const BACKFILL_MAX_PAGES: usize = 100;
+// Discord's upload limit for bots.
+const EXPORT_MAX_BYTES: usize = 8 * 1024 * 1024;
+// How far back a dry run reads a channel that isn't tracked yet.
+const DRY_RUN_SCAN_LIMIT: usize = 1000;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn unix_now() -> i64 {
+    (now_ms() / 1000) as i64
+}
+
+// Message ids are snowflakes whose upper bits hold the creation time, so
+// every message sent before `ms` has an id below the one returned here.
+fn snowflake_at(ms: u64) -> i64 {
+    (ms.saturating_sub(DISCORD_EPOCH_MS) << 22) as i64
+}
+
+fn snowflake_before(age: Duration) -> i64 {
+    snowflake_at(now_ms().saturating_sub(age.as_millis() as u64))
+}
+
+fn snowflake_time(message_id: i64) -> DateTime<Utc> {
+    DateTime::from(
+        UNIX_EPOCH + Duration::from_millis(((message_id as u64) >> 22) + DISCORD_EPOCH_MS),
+    )
+}
+
+fn retry_delay(attempts: i32) -> i64 {
+    (DELETE_RETRY_BASE << attempts.clamp(0, 16)).min(DELETE_RETRY_MAX)
+}
+
+// Tracked messages the window may delete. Exempt messages neither get deleted
+// nor count towards the window size.
+fn trimmable(config: &HistoryWindowConfig) -> HistoryQuery<'static, Pg> {
+    let mut query = history::history
+        .filter(history::channel_id.eq(config.channel_id))
+        .filter(not(history::author_id.eq_any(config.exempt_users.clone())))
+        .filter(not(
+            history::author_roles.overlaps_with(config.exempt_roles.clone())
+        ))
+        .into_boxed();
+
+    if config.exempt_pinned {
+        query = query.filter(history::pinned.eq(false));
+    }
+
+    if config.exempt_attachments {
+        query = query.filter(history::has_attachments.eq(false));
+    }
+
+    query
+}
+
+// The same checks as `trimmable`, for messages that aren't tracked yet.
+fn is_trimmable(record: &History, config: &HistoryWindowConfig) -> bool {
+    !(config.exempt_users.contains(&record.author_id)
+        || record
+            .author_roles
+            .iter()
+            .any(|x| config.exempt_roles.contains(x))
+        || config.exempt_pinned && record.pinned
+        || config.exempt_attachments && record.has_attachments)
+}
+
+// How many of `records`, newest first, `find_expired` would pick if they were
+// tracked; `cutoff` is the age limit as a message id.
+fn count_expired(records: &[History], config: &HistoryWindowConfig, cutoff: Option<i64>) -> usize {
+    records
+        .iter()
+        .filter(|x| is_trimmable(x, config))
+        .enumerate()
+        .filter(|(i, x)| {
+            config.window_size.is_some_and(|size| *i >= size as usize)
+                || cutoff.is_some_and(|cutoff| x.message_id < cutoff)
+        })
+        .count()
+}
+
+// Every tracked message past the count or age limit.
+fn find_expired(conn: &PgConnection, config: &HistoryWindowConfig) -> QueryResult<Vec<History>> {
+    let mut expired = Vec::new();
+
+    if let Some(window_size) = config.window_size {
+        expired.append(
+            &mut trimmable(config)
+                .order(history::message_id.desc())
+                .offset(window_size as i64)
+                .load::<History>(conn)?,
+        );
+    }
+
+    if let Some(max_age) = config.max_age {
+        let cutoff = snowflake_before(Duration::from_secs(max_age as u64));
+        expired.append(
+            &mut trimmable(config)
+                .filter(history::message_id.lt(cutoff))
+                .load::<History>(conn)?,
+        );
+    }
+
+    expired.sort_by_key(|x| x.message_id);
+    expired.dedup_by_key(|x| x.message_id);
+
+    Ok(expired)
+}
+
+// Moves every message past the count or age limit from the history into the
+// deletion queue, due right away, so a crash between here and the Discord call
+// can't lose track of it.
+fn queue_expired(conn: &PgConnection, config: &HistoryWindowConfig) -> QueryResult<()> {
+    let expired = find_expired(conn, config)?;
+
+    let ids: Vec<_> = expired.iter().map(|x| x.message_id).collect();
+    diesel::delete(history::history.filter(history::message_id.eq_any(ids))).execute(conn)?;
+
+    let pending: Vec<_> = expired
+        .into_iter()
+        .map(|x| PendingDeletion {
+            message_id: x.message_id,
+            channel_id: x.channel_id,
+            attempts: 0,
+            next_attempt: unix_now(),
+            last_error: None,
+            dead: false,
+            archive: config.archive,
+        })
+        .collect();
+
+    diesel::insert_into(deletion_queue::deletion_queue)
+        .values(&pending)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Claims the queued deletions that are due for an attempt, oldest first, in
+// one channel or everywhere. At most `DELETE_CLAIM_LIMIT` are claimed, so
+// they are done before the lease runs out.
+fn claim_due(conn: &PgConnection, channel_id: Option<i64>) -> QueryResult<Vec<PendingDeletion>> {
+    let now = unix_now();
+    let mut due = deletion_queue::deletion_queue
+        .select(deletion_queue::message_id)
+        .filter(deletion_queue::dead.eq(false))
+        .filter(deletion_queue::next_attempt.le(now))
+        .into_boxed();
+    if let Some(channel_id) = channel_id {
+        due = due.filter(deletion_queue::channel_id.eq(channel_id));
+    }
+    let due = due
+        .order(deletion_queue::message_id)
+        .limit(DELETE_CLAIM_LIMIT);
+
+    // Checked again so rows another sweep claimed in the meantime are skipped.
+    diesel::update(
+        deletion_queue::deletion_queue
+            .filter(deletion_queue::message_id.eq_any(due))
+            .filter(deletion_queue::dead.eq(false))
+            .filter(deletion_queue::next_attempt.le(now)),
+    )
+    .set(deletion_queue::next_attempt.eq(now + DELETE_LEASE))
+    .get_results(conn)
+}
+
+// Queues the channel's expired messages and claims its due deletions.
+fn take_expired(
+    conn: &PgConnection,
+    config: &HistoryWindowConfig,
+) -> QueryResult<Vec<PendingDeletion>> {
+    conn.transaction(|| {
+        queue_expired(conn, config)?;
+        claim_due(conn, Some(config.channel_id))
+    })
+}
+
+// Deletions claimed by a previous run are still leased; make everything
+// pending due so the first sweep picks it up.
+pub async fn reconcile_deletions(db: &Database) -> Result<usize> {
+    db.run(|conn| {
+        diesel::update(
+            deletion_queue::deletion_queue
+                .filter(deletion_queue::dead.eq(false))
+                .filter(deletion_queue::next_attempt.gt(unix_now())),
+        )
+        .set(deletion_queue::next_attempt.eq(unix_now()))
+        .execute(conn)
+    })
+    .await
+}
+
+fn is_active(config: &HistoryWindowConfig) -> bool {
+    config.window_size.is_some() || config.max_age.is_some()
+}
+
+// Whether a broader row could apply, which needs the channel's category, or
+// whether it is a thread at all. Both cost an API call.
+fn needs_channel(configs: &[HistoryWindowConfig], channel_id: i64) -> bool {
+    !configs.is_empty() && !configs.iter().any(|x| x.channel_id == channel_id)
+}
+
+// The channel's own row overrides its category's, which overrides the guild
+// default. A row without limits switches the window off at its scope.
+fn resolve(
+    mut configs: Vec<HistoryWindowConfig>,
+    channel_id: i64,
+    parent_id: Option<i64>,
+) -> Option<HistoryWindowConfig> {
+    let index = configs
+        .iter()
+        .position(|x| x.channel_id == channel_id)
+        .or_else(|| {
+            configs
+                .iter()
+                .position(|x| x.category && Some(x.channel_id) == parent_id)
+        })
+        .or_else(|| configs.iter().position(|x| x.channel_id == 0))?;
+
+    Some(configs.swap_remove(index))
+}
+
+fn candidates(
+    configs: &[HistoryWindowConfig],
+    guild_id: i64,
+    channel_id: i64,
+) -> Vec<HistoryWindowConfig> {
+    configs
+        .iter()
+        .filter(|x| {
+            x.channel_id == channel_id
+                || guild_id != 0
+                    && x.guild_id == guild_id
+                    && (x.channel_id == 0 || x.category || x.threads)
+        })
+        .cloned()
+        .collect()
+}
+
+// Resolves the channel's policy from its guild's rows. A thread without a row
+// of its own, forum posts included, follows its parent channel's policy, but
+// only when that policy opts threads in. None when the channel is gone.
+async fn resolve_channel(
+    context: &Context,
+    configs: &[HistoryWindowConfig],
+    guild_id: i64,
+    channel_id: i64,
+) -> Result<Option<Option<HistoryWindowConfig>>> {
+    let configs = candidates(configs, guild_id, channel_id);
+    if !needs_channel(&configs, channel_id) {
+        return Ok(Some(resolve(configs, channel_id, None)));
+    }
+
+    let channel = match context.channel(channel_id as u64).await? {
+        Some(channel) => channel,
+        None => return Ok(None),
+    };
+    let parent_id = channel.parent_id.map(|x| x as i64);
+    if channel.kind != ChannelKind::Thread {
+        return Ok(Some(resolve(configs, channel_id, parent_id)));
+    }
+
+    let parent_id = match parent_id {
+        Some(parent_id) => parent_id,
+        None => return Ok(Some(None)),
+    };
+    let category_id = if needs_channel(&configs, parent_id) {
+        match context.channel(parent_id as u64).await? {
+            Some(parent) => parent.parent_id.map(|x| x as i64),
+            None => return Ok(None),
+        }
+    } else {
+        None
+    };
+
+    Ok(Some(
+        resolve(configs, parent_id, category_id).filter(|x| x.threads),
+    ))
+}
+
+// The row that governs the channel, whichever scope it was set at.
+async fn load_policy(
+    context: &Context,
+    guild_id: Option<u64>,
+    channel_id: u64,
+) -> Result<Option<HistoryWindowConfig>> {
+    let guild_id = guild_id.unwrap_or(0) as i64;
+    let channel_id = channel_id as i64;
+
+    let configs = context
+        .db
+        .run(move |conn| {
+            history_window_config::history_window_config
+                .filter(
+                    history_window_config::channel_id.eq(channel_id).or(
+                        history_window_config::guild_id.eq(guild_id).and(
+                            history_window_config::channel_id
+                                .eq(0)
+                                .or(history_window_config::category.eq(true))
+                                .or(history_window_config::threads.eq(true)),
+                        ),
+                    ),
+                )
+                .load::<HistoryWindowConfig>(conn)
+        })
+        .await?;
+
+    Ok(resolve_channel(context, &configs, guild_id, channel_id)
+        .await?
+        .flatten())
+}
+
+// Messages fetched over HTTP carry no guild, so the caller names it.
+fn record(message_id: u64, message: &Message, guild_id: Option<u64>) -> History {
+    History {
+        message_id: message_id as i64,
+        channel_id: message.channel_id as i64,
+        author_id: message.author.id as i64,
+        author_roles: message.author_roles.iter().map(|x| *x as i64).collect(),
+        has_attachments: !message.attachments.is_empty(),
+        pinned: message.pinned,
+        guild_id: guild_id.unwrap_or(0) as i64,
+    }
+}
+
+// Attachments are kept as links only; Discord stops serving them once the
+// message is deleted, so only their names and types stay useful.
+fn archive(message_id: i64, message: &Message, trimmed: bool) -> ArchivedMessage {
+    let attachments = message
+        .attachments
+        .iter()
+        .map(|x| json!({ "filename": x.filename, "content_type": x.content_type, "url": x.url }))
+        .collect();
+    let embeds = message
+        .embeds
+        .iter()
+        .map(|x| {
+            json!({
+                "author_name": x.author_name,
+                "avatar_url": x.avatar_url,
+                "description": x.description,
+                "footer": x.footer,
+                "image": x.image,
+            })
+        })
+        .collect();
+
+    ArchivedMessage {
+        message_id,
+        channel_id: message.channel_id as i64,
+        author_id: message.author.id as i64,
+        author_name: message.author.name.clone(),
+        content: message.content.clone(),
+        attachments: Value::Array(attachments),
+        embeds: Value::Array(embeds),
+        trimmed,
+    }
+}
+
+// Messages queued in archive mode join the archive before they go. Most were
+// copied when they were recorded; those recorded before archiving was switched
+// on are fetched one by one. A message that can't be fetched stays queued
+// instead of being deleted unarchived. Copies of messages deleted without
+// archiving are dropped.
+async fn archive_messages(
+    context: &Context,
+    messages: Vec<PendingDeletion>,
+) -> Result<(Vec<PendingDeletion>, Vec<(PendingDeletion, String)>)> {
+    if messages.is_empty() {
+        return Ok((messages, Vec::new()));
+    }
+
+    let (archived, plain): (Vec<_>, Vec<_>) = messages.iter().partition(|x| x.archive);
+    let archived: Vec<_> = archived.iter().map(|x| x.message_id).collect();
+    let plain: Vec<_> = plain.iter().map(|x| x.message_id).collect();
+    let copied = context
+        .db
+        .run(move |conn| {
+            conn.transaction(|| {
+                diesel::delete(
+                    message_archive::message_archive
+                        .filter(message_archive::message_id.eq_any(plain))
+                        .filter(message_archive::trimmed.eq(false)),
+                )
+                .execute(conn)?;
+
+                diesel::update(
+                    message_archive::message_archive
+                        .filter(message_archive::message_id.eq_any(archived)),
+                )
+                .set(message_archive::trimmed.eq(true))
+                .returning(message_archive::message_id)
+                .get_results::<i64>(conn)
+            })
+        })
+        .await?;
+
+    let mut ready = Vec::new();
+    let mut failed = Vec::new();
+    let mut archives = Vec::new();
+    for message in messages {
+        if !message.archive || copied.contains(&message.message_id) {
+            ready.push(message);
+            continue;
+        }
+
+        let fetched = context
+            .callbacks
+            .fetch_message(message.channel_id as u64, message.message_id as u64)
+            .await;
+
+        match fetched {
+            Some(fetched) => {
+                archives.push(archive(message.message_id, &fetched, true));
+                ready.push(message);
+            }
+            None => failed.push((
+                message,
+                "could not fetch the message to archive".to_string(),
+            )),
+        }
+    }
+
+    if !archives.is_empty() {
+        context
+            .db
+            .run(move |conn| {
+                diesel::insert_into(message_archive::message_archive)
+                    .values(archives)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+            })
+            .await?;
+    }
+
+    Ok((ready, failed))
+}
+
+// Splits one channel's messages into bulk delete batches and the ones that
+// must go one by one: anything older than `cutoff` and a lone leftover.
+fn bulk_batches(
+    messages: Vec<PendingDeletion>,
+    cutoff: i64,
+) -> (Vec<Vec<PendingDeletion>>, Vec<PendingDeletion>) {
+    let (mut recent, mut single): (Vec<_>, Vec<_>) =
+        messages.into_iter().partition(|x| x.message_id >= cutoff);
+
+    let mut batches = Vec::new();
+    while recent.len() >= 2 {
+        batches.push(
+            recent
+                .drain(..recent.len().min(BULK_DELETE_MAX_COUNT))
+                .collect(),
+        );
+    }
+    single.append(&mut recent);
+
+    (batches, single)
+}
+
+// Recent messages go out in bulk batches; anything too old for bulk delete,
+// a lone leftover or a failed batch is deleted one by one. Deleted messages
+// leave the queue, failures are retried with backoff until they run out of
+// attempts.
+async fn delete_messages(context: &Context, messages: Vec<PendingDeletion>) -> Result<()> {
+    let (messages, mut failed) = archive_messages(context, messages).await?;
+
+    let cutoff = snowflake_before(BULK_DELETE_MAX_AGE);
+    let mut channels: HashMap<i64, Vec<PendingDeletion>> = HashMap::new();
+    for message in messages {
+        channels
+            .entry(message.channel_id)
+            .or_default()
+            .push(message);
+    }
+
+    let mut deleted = Vec::new();
+    for (channel_id, messages) in channels {
+        let (batches, mut single) = bulk_batches(messages, cutoff);
+
+        for batch in batches {
+            let ids: Vec<_> = batch.iter().map(|x| x.message_id as u64).collect();
+
+            match context
+                .callbacks
+                .delete_messages(channel_id as u64, &ids)
+                .await
+            {
+                Ok(()) => deleted.extend(batch.iter().map(|x| x.message_id)),
+                Err(_) => single.extend(batch),
+            }
+        }
+
+        for message in single {
+            let result = context
+                .callbacks
+                .delete_message(channel_id as u64, message.message_id as u64)
+                .await;
+
+            match result {
+                Ok(()) => deleted.push(message.message_id),
+                Err(error) => failed.push((message, error.to_string())),
+            }
+        }
+    }
+
+    HISTORY_WINDOW_DELETED.inc_by(deleted.len() as u64);
+
+    let dead = context
+        .db
+        .run(move |conn| {
+            conn.transaction(|| {
+                diesel::delete(
+                    deletion_queue::deletion_queue
+                        .filter(deletion_queue::message_id.eq_any(deleted)),
+                )
+                .execute(conn)?;
+
+                let mut dead = 0;
+                for (message, error) in failed {
+                    let attempts = message.attempts + 1;
+                    let is_dead = attempts >= DELETE_MAX_ATTEMPTS;
+                    if is_dead {
+                        warn!(
+                            message_id = message.message_id,
+                            channel_id = message.channel_id,
+                            %error,
+                            "giving up on deleting message"
+                        );
+                        dead += 1;
+                    }
+
+                    diesel::update(deletion_queue::deletion_queue.find(message.message_id))
+                        .set((
+                            deletion_queue::attempts.eq(attempts),
+                            deletion_queue::next_attempt.eq(unix_now() + retry_delay(attempts)),
+                            deletion_queue::last_error.eq(Some(error)),
+                            deletion_queue::dead.eq(is_dead),
+                        ))
+                        .execute(conn)?;
+                }
+
+                Ok(dead)
+            })
+        })
+        .await?;
+
+    HISTORY_WINDOW_DEAD_LETTERED.inc_by(dead);
+
+    Ok(())
+}
+
 #[async_trait]
 impl MessageHandler for HistoryWindow {
+    fn feature(&self) -> Option<&'static str> {
+        Some("history_window")
+    }
+
     async fn on_message(
-        &mut self,
+        &self,
         (message_id, message): &IncomingMessage,
         context: &Context,
-    ) -> Result<(), Box<dyn Error>> {
-        let channel_id = message.channel_id;
-
-        let messages_to_delete = {
-            let conn = context.db_conn.lock().unwrap();
-            let window_size = history_window_config::history_window_config
-                .select(history_window_config::window_size)
-                .find(channel_id as i64)
-                .first::<i32>(&*conn)
-                .optional()?;
+    ) -> Result<()> {
+        let config = match load_policy(context, message.guild_id, message.channel_id).await? {
+            Some(config) if is_active(&config) => HistoryWindowConfig {
+                channel_id: message.channel_id as i64,
+                ..config
+            },
+            _ => return Ok(()),
+        };
+        let record = record(*message_id, message, message.guild_id);
+        // Copied now, while the content is at hand, so archiving needs no
+        // fetch when the message goes.
+        let snapshot = config
+            .archive
+            .then(|| archive(*message_id as i64, message, false));
 
-            let window_size = if let Some(window_size) = window_size {
-                window_size as i64
-            } else {
-                return Ok(());
-            };
+        let messages_to_delete = context
+            .db
+            .run(move |conn| {
+                diesel::insert_into(history::history)
+                    .values(record)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
 
-            diesel::insert_into(history::history)
-                .values(History {
-                    channel_id: channel_id as i64,
-                    message_id: *message_id as i64,
-                })
-                .execute(&*conn)?;
+                if let Some(snapshot) = snapshot {
+                    diesel::insert_into(message_archive::message_archive)
+                        .values(snapshot)
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
 
-            let messages_to_delete = history::history
-                .filter(history::channel_id.eq(channel_id as i64))
-                .order(history::message_id.desc())
-                .offset(window_size)
-                .load::<History>(&*conn)?;
+                take_expired(conn, &config)
+            })
+            .await?;
+
+        delete_messages(context, messages_to_delete).await
+    }
+}
+
+// Pinning arrives as a message update, which keeps the pinned exemption current.
+#[async_trait]
+impl MessageUpdateHandler for HistoryWindow {
+    async fn on_message_update(&self, update: &MessageUpdate, context: &Context) -> Result<()> {
+        let (message_id, pinned) = match update.pinned {
+            Some(pinned) => (update.id as i64, pinned),
+            None => return Ok(()),
+        };
 
-            for message in &messages_to_delete {
-                diesel::delete(history::history)
-                    .filter(history::message_id.eq(message.message_id as i64))
-                    .execute(&*conn)?;
+        context
+            .db
+            .run(move |conn| {
+                diesel::update(history::history.find(message_id))
+                    .set(history::pinned.eq(pinned))
+                    .execute(conn)
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+// Messages deleted by someone else must not keep occupying window slots.
+async fn forget_messages(context: &Context, message_ids: Vec<i64>) -> Result<()> {
+    context
+        .db
+        .run(move |conn| {
+            diesel::delete(history::history.filter(history::message_id.eq_any(&message_ids)))
+                .execute(conn)?;
+            diesel::delete(
+                message_archive::message_archive
+                    .filter(message_archive::message_id.eq_any(&message_ids))
+                    .filter(message_archive::trimmed.eq(false)),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                deletion_queue::deletion_queue
+                    .filter(deletion_queue::message_id.eq_any(&message_ids)),
+            )
+            .execute(conn)
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl MessageDeleteHandler for HistoryWindow {
+    async fn on_message_delete(&self, delete: &MessageDelete, context: &Context) -> Result<()> {
+        forget_messages(context, vec![delete.id as i64]).await
+    }
+}
+
+#[async_trait]
+impl MessageDeleteBulkHandler for HistoryWindow {
+    async fn on_message_delete_bulk(
+        &self,
+        delete: &MessageDeleteBulk,
+        context: &Context,
+    ) -> Result<()> {
+        forget_messages(context, delete.ids.iter().map(|x| *x as i64).collect()).await
+    }
+}
+
+// A deleted channel takes its messages with it, so nothing is left to track,
+// archive or delete; a deleted category's or channel's own settings go too.
+async fn forget_channel(context: &Context, guild_id: i64, channel_id: i64) -> Result<()> {
+    context
+        .db
+        .run(move |conn| {
+            conn.transaction(|| {
+                diesel::delete(history::history.filter(history::channel_id.eq(channel_id)))
+                    .execute(conn)?;
+                diesel::delete(
+                    message_archive::message_archive
+                        .filter(message_archive::channel_id.eq(channel_id))
+                        .filter(message_archive::trimmed.eq(false)),
+                )
+                .execute(conn)?;
+                diesel::delete(
+                    deletion_queue::deletion_queue
+                        .filter(deletion_queue::channel_id.eq(channel_id)),
+                )
+                .execute(conn)?;
+                diesel::delete(
+                    history_window_config::history_window_config
+                        .filter(history_window_config::guild_id.eq(guild_id))
+                        .filter(history_window_config::channel_id.eq(channel_id)),
+                )
+                .execute(conn)
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl ChannelDeleteHandler for HistoryWindow {
+    async fn on_channel_delete(&self, channel: &Channel, context: &Context) -> Result<()> {
+        let guild_id = channel.guild_id.unwrap_or(0) as i64;
+        forget_channel(context, guild_id, channel.id as i64).await
+    }
+}
+
+// Deletes messages that outlived their channel's max age, so quiet channels
+// are cleaned up too, and retries queued deletions that failed before.
+// Channels no longer covered by any window stop being tracked, and channels
+// deleted while the bot wasn't watching are forgotten.
+#[async_trait]
+impl TimerHandler for HistoryWindow {
+    async fn on_timer(&self, context: &Context) -> Result<()> {
+        let (channels, configs) = context
+            .db
+            .run(move |conn| {
+                let channels = history::history
+                    .select((history::guild_id, history::channel_id))
+                    .distinct()
+                    .load::<(i64, i64)>(conn)?;
+                let configs = history_window_config::history_window_config
+                    .load::<HistoryWindowConfig>(conn)?;
+
+                Ok((channels, configs))
+            })
+            .await?;
+
+        let mut policies = Vec::new();
+        for (guild_id, channel_id) in channels {
+            // A channel where the feature is switched off keeps its tracked
+            // messages untouched until it is switched back on.
+            let guild = Some(guild_id as u64).filter(|x| *x != 0);
+            let features = context.features.get(guild, &context.db).await?;
+            if !features.enabled(MessageHandler::feature(self), channel_id as u64) {
+                continue;
             }
 
-            messages_to_delete
+            let policy = match resolve_channel(context, &configs, guild_id, channel_id).await {
+                Ok(Some(policy)) => policy,
+                Ok(None) => {
+                    forget_channel(context, guild_id, channel_id).await?;
+                    continue;
+                }
+                // Without the channel the policy is unknown; try again next
+                // time.
+                Err(error) => {
+                    warn!(%error, channel_id, "failed to resolve the channel's policy");
+                    continue;
+                }
+            };
+            let policy = policy.filter(is_active).map(|config| HistoryWindowConfig {
+                channel_id,
+                ..config
+            });
+            policies.push((guild_id, channel_id, policy));
+        }
+
+        let messages_to_delete = context
+            .db
+            .run(move |conn| {
+                for (guild_id, channel_id, policy) in policies {
+                    match policy {
+                        Some(config) if config.max_age.is_some() => {
+                            queue_expired(conn, &config)?;
+                        }
+                        Some(_) => {}
+                        None => {
+                            let untracked = history::history
+                                .filter(history::guild_id.eq(guild_id))
+                                .filter(history::channel_id.eq(channel_id));
+                            diesel::delete(
+                                message_archive::message_archive
+                                    .filter(
+                                        message_archive::message_id
+                                            .eq_any(untracked.select(history::message_id)),
+                                    )
+                                    .filter(message_archive::trimmed.eq(false)),
+                            )
+                            .execute(conn)?;
+                            diesel::delete(untracked).execute(conn)?;
+                        }
+                    }
+                }
+
+                claim_due(conn, None)
+            })
+            .await?;
+
+        delete_messages(context, messages_to_delete).await
+    }
+}
+
+// Channels being backfilled; a second backfill of the same channel would only
+// page through the same history again.
+static BACKFILLS: Lazy<Mutex<HashSet<u64>>> = Lazy::new(Default::default);
+
+// Marks a channel as being backfilled until dropped.
+struct BackfillGuard(u64);
+
+impl BackfillGuard {
+    // None when `--backfill` wasn't given. Only the channel the command runs
+    // in is backfilled, so the flag can't go with a wider scope.
+    fn claim(args: &Args, channel_id: u64) -> Result<Option<Self>> {
+        if !args.flag("backfill") {
+            return Ok(None);
+        }
+
+        if args.flag("guild") || args.flag("category") {
+            return Err(Error::UserInput(Text::new("meslimit.backfill_scope")));
+        }
+
+        if !BACKFILLS.lock().unwrap().insert(channel_id) {
+            return Err(Error::UserInput(Text::new("meslimit.backfill_running")));
+        }
+
+        Ok(Some(BackfillGuard(channel_id)))
+    }
+}
+
+impl Drop for BackfillGuard {
+    fn drop(&mut self) {
+        BACKFILLS.lock().unwrap().remove(&self.0);
+    }
+}
+
+// Records the messages posted before the window was enabled, then trims them
+// like any other tracked message. Messages fetched over HTTP carry no member
+// data, so role exemptions cannot apply to them. Reading stops after
+// `BACKFILL_MAX_PAGES` pages. Progress reports are best effort: a slash
+// command's followups stop working after 15 minutes.
+async fn backfill(context: &Context, message: &Message) -> Result<()> {
+    let author_filter = MessageHandler::author_filter(&HistoryWindow);
+    let progress = |text: Text| async move {
+        let sent = async {
+            let text = context.text(message.guild_id, text).await?;
+            context.callbacks.send_message(message.reply(&text)).await
         };
+        if let Err(error) = sent.await {
+            warn!(%error, "failed to report backfill progress");
+        }
+    };
 
-        for message in messages_to_delete {
-            let _ = context
-                .callbacks
-                .delete_message(message.channel_id as u64, message.message_id as u64)
+    progress(Text::new("meslimit.backfill_started")).await;
+
+    let config = load_policy(context, message.guild_id, message.channel_id)
+        .await?
+        .filter(is_active)
+        .map(|config| HistoryWindowConfig {
+            channel_id: message.channel_id as i64,
+            ..config
+        });
+    let archiving = config.as_ref().is_some_and(|x| x.archive);
+
+    let mut before = None;
+    let mut fetched = 0;
+    let mut recorded = 0;
+    let mut capped = true;
+    for _ in 0..BACKFILL_MAX_PAGES {
+        let page = context
+            .callbacks
+            .fetch_history(message.channel_id, before, BACKFILL_PAGE_SIZE)
+            .await?;
+        before = match page.last() {
+            Some((message_id, _)) => Some(*message_id),
+            None => {
+                capped = false;
+                break;
+            }
+        };
+
+        let accepted: Vec<_> = page
+            .iter()
+            .filter(|(_, x)| author_filter.accepts(&x.author, context.current_user_id))
+            .collect();
+        let records: Vec<_> = accepted
+            .iter()
+            .map(|(message_id, x)| record(*message_id, x, message.guild_id))
+            .collect();
+        let snapshots: Vec<_> = accepted
+            .iter()
+            .filter(|_| archiving)
+            .map(|(message_id, x)| archive(*message_id as i64, x, false))
+            .collect();
+        recorded += records.len();
+
+        context
+            .db
+            .run(move |conn| {
+                diesel::insert_into(history::history)
+                    .values(records)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                if !snapshots.is_empty() {
+                    diesel::insert_into(message_archive::message_archive)
+                        .values(snapshots)
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        if (fetched + page.len()) / BACKFILL_REPORT_INTERVAL > fetched / BACKFILL_REPORT_INTERVAL {
+            progress(Text::new("meslimit.backfill_progress").param("count", fetched + page.len()))
                 .await;
         }
+        fetched += page.len();
 
-        Ok(())
+        if page.len() < BACKFILL_PAGE_SIZE as usize {
+            capped = false;
+            break;
+        }
     }
+
+    if capped {
+        progress(Text::new("meslimit.backfill_capped").param("count", fetched)).await;
+    }
+
+    let messages_to_delete = context
+        .db
+        .run(move |conn| match config {
+            Some(config) => take_expired(conn, &config),
+            None => Ok(Vec::new()),
+        })
+        .await?;
+    let deleted = messages_to_delete.len();
+
+    delete_messages(context, messages_to_delete).await?;
+
+    progress(
+        Text::new("meslimit.backfill_done")
+            .param("recorded", recorded)
+            .param("deleted", deleted),
+    )
+    .await;
+
+    Ok(())
 }
 
 pub struct HistoryWindowConfigurator;
 
+// The row a command edits: the channel's own, its category's (`--category`)
+// or the guild default (`--guild`).
+#[derive(Clone, Copy)]
+struct Target {
+    guild_id: i64,
+    channel_id: i64,
+    category: bool,
+}
+
+impl Target {
+    async fn from_args(args: &Args, message: &Message, context: &Context) -> Result<Self> {
+        let guild_id = message
+            .guild_id
+            .ok_or_else(|| Error::Permission(Text::new("command.guild_only")))?;
+
+        let (channel_id, category) = if args.flag("guild") {
+            (0, false)
+        } else if args.flag("category") {
+            // A thread's parent is a channel, not a category.
+            let parent_id = context
+                .channel(message.channel_id)
+                .await?
+                .filter(|x| x.kind != ChannelKind::Thread)
+                .and_then(|x| x.parent_id)
+                .ok_or_else(|| Error::UserInput(Text::new("meslimit.no_category")))?;
+            (parent_id, true)
+        } else {
+            (message.channel_id, false)
+        };
+
+        Ok(Target {
+            guild_id: guild_id as i64,
+            channel_id: channel_id as i64,
+            category,
+        })
+    }
+
+    // Rows written before guild ids were stored join their guild the next
+    // time they are configured.
+    fn adopt(&self, conn: &PgConnection) -> QueryResult<usize> {
+        diesel::update(
+            history_window_config::history_window_config
+                .filter(history_window_config::guild_id.eq(0))
+                .filter(history_window_config::channel_id.eq(self.channel_id)),
+        )
+        .set(history_window_config::guild_id.eq(self.guild_id))
+        .execute(conn)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Limit {
+    Count(Option<i32>),
+    Age(Option<i64>),
+}
+
+// Sets only the given limits, leaving the rest of the row alone. Clearing
+// every limit keeps the row, which switches the window off at that scope.
+async fn set_limits(context: &Context, target: Target, limits: Vec<Limit>) -> Result<()> {
+    context
+        .db
+        .run(move |conn| {
+            conn.transaction(|| {
+                target.adopt(conn)?;
+
+                for limit in limits {
+                    let (window_size, max_age) = match limit {
+                        Limit::Count(size) => (size, None),
+                        Limit::Age(age) => (None, age),
+                    };
+
+                    let insert = diesel::insert_into(history_window_config::history_window_config)
+                        .values((
+                            history_window_config::guild_id.eq(target.guild_id),
+                            history_window_config::channel_id.eq(target.channel_id),
+                            history_window_config::category.eq(target.category),
+                            history_window_config::window_size.eq(window_size),
+                            history_window_config::max_age.eq(max_age),
+                        ))
+                        .on_conflict((
+                            history_window_config::guild_id,
+                            history_window_config::channel_id,
+                        ))
+                        .do_update();
+
+                    match limit {
+                        Limit::Count(_) => insert
+                            .set(history_window_config::window_size.eq(window_size))
+                            .execute(conn)?,
+                        Limit::Age(_) => insert
+                            .set(history_window_config::max_age.eq(max_age))
+                            .execute(conn)?,
+                    };
+                }
+
+                Ok(())
+            })
+        })
+        .await
+}
+
+// Drops the row so the broader scopes apply again.
+async fn reset(context: &Context, target: Target) -> Result<()> {
+    context
+        .db
+        .run(move |conn| {
+            target.adopt(conn)?;
+
+            diesel::delete(
+                history_window_config::history_window_config
+                    .find((target.guild_id, target.channel_id)),
+            )
+            .execute(conn)
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn switch(args: &Args, index: usize) -> Result<bool> {
+    match args.get(index) {
+        Some("on") => Ok(true),
+        Some("off") => Ok(false),
+        _ => Err(Error::Usage),
+    }
+}
+
+async fn update_config(
+    context: &Context,
+    target: Target,
+    update: impl FnOnce(&mut HistoryWindowConfig) + Send + 'static,
+) -> Result<()> {
+    let updated = context
+        .db
+        .run(move |conn| {
+            conn.transaction(|| {
+                target.adopt(conn)?;
+                let row = history_window_config::history_window_config
+                    .find((target.guild_id, target.channel_id));
+
+                let config = row
+                    .for_update()
+                    .first::<HistoryWindowConfig>(conn)
+                    .optional()?;
+
+                let mut config = match config {
+                    Some(config) => config,
+                    None => return Ok(false),
+                };
+                update(&mut config);
+
+                diesel::update(row)
+                    .set((
+                        history_window_config::exempt_pinned.eq(config.exempt_pinned),
+                        history_window_config::exempt_attachments.eq(config.exempt_attachments),
+                        history_window_config::exempt_users.eq(config.exempt_users),
+                        history_window_config::exempt_roles.eq(config.exempt_roles),
+                        history_window_config::archive.eq(config.archive),
+                        history_window_config::threads.eq(config.threads),
+                    ))
+                    .execute(conn)?;
+
+                Ok(true)
+            })
+        })
+        .await?;
+
+    if updated {
+        Ok(())
+    } else {
+        Err(Error::UserInput(Text::new("meslimit.not_enabled")))
+    }
+}
+
+// The id of the day's first possible message, for days the ids can express:
+// from the Discord epoch for 2^41 ms, as ids are signed.
+fn day_start(date: NaiveDate) -> Option<i64> {
+    let ms = u64::try_from(date.and_hms_opt(0, 0, 0)?.timestamp_millis()).ok()?;
+    (DISCORD_EPOCH_MS..DISCORD_EPOCH_MS + (1 << 41))
+        .contains(&ms)
+        .then(|| snowflake_at(ms))
+}
+
+fn date(args: &Args, index: usize) -> Result<NaiveDate> {
+    let input = args.get(index).ok_or(Error::Usage)?;
+
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map_err(|_| Error::UserInput(Text::new("meslimit.date").param("index", index + 1)))
+}
+
+// Sends the channel's archive for the given UTC dates, both inclusive, as a
+// JSON Lines file.
+async fn export(
+    context: &Context,
+    message: &Message,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<()> {
+    if from > to {
+        return Err(Error::UserInput(Text::new("meslimit.date_order")));
+    }
+
+    let channel_id = message.channel_id as i64;
+    let (start, end) = day_start(from)
+        .zip(to.succ_opt().and_then(day_start))
+        .ok_or_else(|| Error::UserInput(Text::new("meslimit.date_range")))?;
+
+    let archives = context
+        .db
+        .run(move |conn| {
+            message_archive::message_archive
+                .filter(message_archive::channel_id.eq(channel_id))
+                .filter(message_archive::trimmed.eq(true))
+                .filter(message_archive::message_id.ge(start))
+                .filter(message_archive::message_id.lt(end))
+                .order(message_archive::message_id)
+                .load::<ArchivedMessage>(conn)
+        })
+        .await?;
+
+    if archives.is_empty() {
+        return Err(Error::UserInput(Text::new("meslimit.export_empty")));
+    }
+
+    let mut lines = String::new();
+    for archive in &archives {
+        let line = json!({
+            "message_id": archive.message_id.to_string(),
+            "created_at": snowflake_time(archive.message_id).to_rfc3339(),
+            "author_id": archive.author_id.to_string(),
+            "author_name": archive.author_name,
+            "content": archive.content,
+            "attachments": archive.attachments,
+            "embeds": archive.embeds,
+        });
+        lines.push_str(&line.to_string());
+        lines.push('\n');
+    }
+
+    if lines.len() > EXPORT_MAX_BYTES {
+        return Err(Error::UserInput(Text::new("meslimit.export_too_large")));
+    }
+
+    let reply = context
+        .text(
+            message.guild_id,
+            Text::new("meslimit.exported").param("count", archives.len()),
+        )
+        .await?;
+    let filename = format!("archive-{}-{}-{}.jsonl", channel_id, from, to);
+
+    context
+        .callbacks
+        .send_message(
+            message
+                .reply(&reply)
+                .add_file(&filename, lines.into_bytes()),
+        )
+        .await
+}
+
+fn scope_key(config: &HistoryWindowConfig, channel_id: i64) -> &'static str {
+    if config.channel_id == channel_id {
+        "meslimit.scope.channel"
+    } else if config.category {
+        "meslimit.scope.category"
+    } else if config.channel_id != 0 {
+        "meslimit.scope.parent"
+    } else {
+        "meslimit.scope.guild"
+    }
+}
+
+async fn status(context: &Context, message: &Message) -> Result<()> {
+    let channel_id = message.channel_id as i64;
+    let config = load_policy(context, message.guild_id, message.channel_id)
+        .await?
+        .ok_or_else(|| Error::UserInput(Text::new("meslimit.not_enabled")))?;
+
+    let (tracked, oldest, pending, dead) = context
+        .db
+        .run(move |conn| {
+            let tracked = history::history
+                .filter(history::channel_id.eq(channel_id))
+                .count()
+                .get_result::<i64>(conn)?;
+            let oldest = history::history
+                .select(history::message_id)
+                .filter(history::channel_id.eq(channel_id))
+                .order(history::message_id)
+                .first::<i64>(conn)
+                .optional()?;
+            let queued =
+                deletion_queue::deletion_queue.filter(deletion_queue::channel_id.eq(channel_id));
+            let pending = queued
+                .filter(deletion_queue::dead.eq(false))
+                .count()
+                .get_result::<i64>(conn)?;
+            let dead = queued
+                .filter(deletion_queue::dead.eq(true))
+                .count()
+                .get_result::<i64>(conn)?;
+
+            Ok((tracked, oldest, pending, dead))
+        })
+        .await?;
+
+    let locale = context.locale(message.guild_id).await?;
+    let none = Text::new("meslimit.status_none").render(locale);
+
+    let text = Text::new("meslimit.status")
+        .param(
+            "scope",
+            Text::new(scope_key(&config, channel_id)).render(locale),
+        )
+        .param(
+            "size",
+            config.window_size.map_or(none.clone(), |x| x.to_string()),
+        )
+        .param(
+            "age",
+            config.max_age.map_or(none.clone(), |x| {
+                format_duration(Duration::from_secs(x as u64))
+            }),
+        )
+        .param("tracked", tracked)
+        .param(
+            "oldest",
+            oldest.map_or(none, |x| format!("<t:{}:f>", snowflake_time(x).timestamp())),
+        )
+        .param("pending", pending)
+        .param("dead", dead)
+        .render(locale);
+
+    context.callbacks.send_message(message.reply(&text)).await
+}
+
+// The latest messages of a channel as they would be recorded, up to
+// `DRY_RUN_SCAN_LIMIT`.
+async fn scan_history(context: &Context, message: &Message) -> Result<Vec<History>> {
+    let channel_id = message.channel_id;
+    let author_filter = MessageHandler::author_filter(&HistoryWindow);
+    let mut records = Vec::new();
+    let mut before = None;
+    while records.len() < DRY_RUN_SCAN_LIMIT {
+        let page = context
+            .callbacks
+            .fetch_history(channel_id, before, BACKFILL_PAGE_SIZE)
+            .await?;
+        before = match page.last() {
+            Some((message_id, _)) => Some(*message_id),
+            None => break,
+        };
+
+        records.extend(
+            page.iter()
+                .filter(|(_, x)| author_filter.accepts(&x.author, context.current_user_id))
+                .map(|(message_id, x)| record(*message_id, x, message.guild_id)),
+        );
+
+        if page.len() < BACKFILL_PAGE_SIZE as usize {
+            break;
+        }
+    }
+    records.truncate(DRY_RUN_SCAN_LIMIT);
+
+    Ok(records)
+}
+
+// Counts what `enable <size>` would delete right now, keeping the channel's
+// age limit and exemptions. A channel nobody tracks yet is read from Discord
+// instead, as far back as `DRY_RUN_SCAN_LIMIT`.
+async fn dry_run(context: &Context, message: &Message, window_size: i32) -> Result<()> {
+    let channel_id = message.channel_id as i64;
+    let config = HistoryWindowConfig {
+        channel_id,
+        window_size: Some(window_size),
+        ..load_policy(context, message.guild_id, message.channel_id)
+            .await?
+            .unwrap_or_default()
+    };
+
+    let tracked = context
+        .db
+        .run(move |conn| {
+            history::history
+                .filter(history::channel_id.eq(channel_id))
+                .count()
+                .get_result::<i64>(conn)
+        })
+        .await?;
+
+    let text = if tracked > 0 {
+        let expired = context
+            .db
+            .run(move |conn| find_expired(conn, &config))
+            .await?
+            .len();
+        Text::new("meslimit.dryrun")
+            .param("tracked", tracked)
+            .param("count", expired)
+    } else {
+        let scanned = match scan_history(context, message).await {
+            Ok(records) => records,
+            Err(error) => {
+                warn!(%error, "failed to read the channel for a dry run");
+                Vec::new()
+            }
+        };
+
+        if scanned.is_empty() {
+            Text::new("meslimit.dryrun_untracked")
+        } else {
+            let cutoff = config
+                .max_age
+                .map(|x| snowflake_before(Duration::from_secs(x as u64)));
+            Text::new("meslimit.dryrun_scanned")
+                .param("scanned", scanned.len())
+                .param("count", count_expired(&scanned, &config, cutoff))
+        }
+    };
+
+    let text = context.text(message.guild_id, text).await?;
+    context.callbacks.send_message(message.reply(&text)).await
+}
+
+// Slash options matching the `--guild` and `--category` flags.
+fn scoped(mut options: Vec<CommandOption>) -> Vec<CommandOption> {
+    options.push(CommandOption::value(
+        "guild",
+        "meslimit.slash.guild",
+        CommandOptionKind::Boolean,
+        false,
+    ));
+    options.push(CommandOption::value(
+        "category",
+        "meslimit.slash.category",
+        CommandOptionKind::Boolean,
+        false,
+    ));
+    options
+}
+
+fn toggle_id(ids: &mut Vec<i64>, id: u64, remove: bool) {
+    let id = id as i64;
+    ids.retain(|x| *x != id);
+    if !remove {
+        ids.push(id);
+    }
+}
+
 #[async_trait]
 impl CommandHandler for HistoryWindowConfigurator {
-    fn accepts(&self, command_name: &str) -> bool {
-        command_name == "meslimit"
+    fn name(&self) -> &'static str {
+        "meslimit"
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        &[
+            "enable <size> [--backfill] [--guild|--category]",
+            "age <duration> [--backfill] [--guild|--category]",
+            "disable [count|age] [--guild|--category]",
+            "reset [--guild|--category]",
+            "exempt pinned|attachments <on|off> [--guild|--category]",
+            "exempt user|role <target> [--remove] [--guild|--category]",
+            "archive <on|off> [--guild|--category]",
+            "threads <on|off> [--guild|--category]",
+            "export <from> <to>",
+            "status",
+            "dryrun <size>",
+        ]
+    }
+
+    fn description(&self) -> &'static str {
+        "meslimit.description"
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        Permissions::MANAGE_MESSAGES
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        Some(SlashCommand::new(
+            self.name(),
+            self.description(),
+            vec![
+                CommandOption::sub_command(
+                    "enable",
+                    "meslimit.slash.enable",
+                    scoped(vec![
+                        CommandOption::value(
+                            "size",
+                            "meslimit.slash.size",
+                            CommandOptionKind::Integer,
+                            true,
+                        ),
+                        CommandOption::value(
+                            "backfill",
+                            "meslimit.slash.backfill",
+                            CommandOptionKind::Boolean,
+                            false,
+                        ),
+                    ]),
+                ),
+                CommandOption::sub_command(
+                    "age",
+                    "meslimit.slash.age",
+                    scoped(vec![
+                        CommandOption::value(
+                            "duration",
+                            "meslimit.slash.duration",
+                            CommandOptionKind::String,
+                            true,
+                        ),
+                        CommandOption::value(
+                            "backfill",
+                            "meslimit.slash.backfill",
+                            CommandOptionKind::Boolean,
+                            false,
+                        ),
+                    ]),
+                ),
+                CommandOption::sub_command(
+                    "disable",
+                    "meslimit.slash.disable",
+                    scoped(vec![CommandOption::value(
+                        "limit",
+                        "meslimit.slash.limit",
+                        CommandOptionKind::String,
+                        false,
+                    )]),
+                ),
+                CommandOption::sub_command(
+                    "exempt",
+                    "meslimit.slash.exempt",
+                    scoped(vec![
+                        CommandOption::value(
+                            "kind",
+                            "meslimit.slash.exempt_kind",
+                            CommandOptionKind::String,
+                            true,
+                        ),
+                        CommandOption::value(
+                            "value",
+                            "meslimit.slash.exempt_value",
+                            CommandOptionKind::String,
+                            true,
+                        ),
+                        CommandOption::value(
+                            "remove",
+                            "meslimit.slash.remove",
+                            CommandOptionKind::Boolean,
+                            false,
+                        ),
+                    ]),
+                ),
+                CommandOption::sub_command(
+                    "archive",
+                    "meslimit.slash.archive",
+                    scoped(vec![CommandOption::value(
+                        "value",
+                        "meslimit.slash.switch",
+                        CommandOptionKind::String,
+                        true,
+                    )]),
+                ),
+                CommandOption::sub_command(
+                    "threads",
+                    "meslimit.slash.threads",
+                    scoped(vec![CommandOption::value(
+                        "value",
+                        "meslimit.slash.switch",
+                        CommandOptionKind::String,
+                        true,
+                    )]),
+                ),
+                CommandOption::sub_command(
+                    "export",
+                    "meslimit.slash.export",
+                    vec![
+                        CommandOption::value(
+                            "from",
+                            "meslimit.slash.from",
+                            CommandOptionKind::String,
+                            true,
+                        ),
+                        CommandOption::value(
+                            "to",
+                            "meslimit.slash.to",
+                            CommandOptionKind::String,
+                            true,
+                        ),
+                    ],
+                ),
+                CommandOption::sub_command("reset", "meslimit.slash.reset", scoped(Vec::new())),
+                CommandOption::sub_command("status", "meslimit.slash.status", vec![]),
+                CommandOption::sub_command(
+                    "dryrun",
+                    "meslimit.slash.dryrun",
+                    vec![CommandOption::value(
+                        "size",
+                        "meslimit.slash.size",
+                        CommandOptionKind::Integer,
+                        true,
+                    )],
+                ),
+            ],
+        ))
     }
 
     async fn handler(
-        &mut self,
-        args: &[&str],
+        &self,
+        args: &Args,
         (_, message): &IncomingMessage,
         context: &Context,
-    ) -> Result<(), Box<dyn Error>> {
-        let reply = {
-            let conn = context.db_conn.lock().unwrap();
-            match args {
-                &["enable", size] => {
-                    if let Ok(size) = size.parse::<i32>() {
-                        if (1..=10000).contains(&size) {
-                            diesel::insert_into(history_window_config::history_window_config)
-                                .values(HistoryWindowConfig {
-                                    channel_id: message.channel_id as i64,
-                                    window_size: size,
-                                })
-                                .on_conflict(history_window_config::channel_id)
-                                .do_update()
-                                .set(history_window_config::window_size.eq(size))
-                                .execute(&*conn)?;
-                            "有効化しました。"
-                        } else {
-                            "1以上10000以下の範囲で指定してください。"
-                        }
-                    } else {
-                        "不正なパラメータです。"
-                    }
-                }
-                &["disable"] => {
-                    diesel::delete(history_window_config::history_window_config)
-                        .filter(history_window_config::channel_id.eq(message.channel_id as i64))
-                        .execute(&*conn)?;
-                    "無効化しました。"
+    ) -> Result<()> {
+        let target = || Target::from_args(args, message, context);
+        let remove = args.flag("remove");
+        let mut backfill_guard = None;
+
+        let reply = match args.as_strs()[..] {
+            ["enable", _] => {
+                let size = args.integer(1, 1..=10000)? as i32;
+                backfill_guard = BackfillGuard::claim(args, message.channel_id)?;
+                set_limits(context, target().await?, vec![Limit::Count(Some(size))]).await?;
+                "meslimit.enabled"
+            }
+            ["age", _] => {
+                let age = args.duration(1)?.as_secs() as i64;
+                backfill_guard = BackfillGuard::claim(args, message.channel_id)?;
+                set_limits(context, target().await?, vec![Limit::Age(Some(age))]).await?;
+                "meslimit.enabled"
+            }
+            ["disable", "count"] => {
+                set_limits(context, target().await?, vec![Limit::Count(None)]).await?;
+                "meslimit.updated"
+            }
+            ["disable", "age"] => {
+                set_limits(context, target().await?, vec![Limit::Age(None)]).await?;
+                "meslimit.updated"
+            }
+            ["disable"] => {
+                let limits = vec![Limit::Count(None), Limit::Age(None)];
+                set_limits(context, target().await?, limits).await?;
+                "meslimit.disabled"
+            }
+            ["reset"] => {
+                reset(context, target().await?).await?;
+                "meslimit.reset"
+            }
+            ["exempt", "pinned", _] => {
+                let on = switch(args, 2)?;
+                update_config(context, target().await?, move |x| x.exempt_pinned = on).await?;
+                "meslimit.updated"
+            }
+            ["exempt", "attachments", _] => {
+                let on = switch(args, 2)?;
+                update_config(context, target().await?, move |x| x.exempt_attachments = on).await?;
+                "meslimit.updated"
+            }
+            ["exempt", "user", _] => {
+                let user_id = args.user(2)?;
+                update_config(context, target().await?, move |x| {
+                    toggle_id(&mut x.exempt_users, user_id, remove)
+                })
+                .await?;
+                "meslimit.updated"
+            }
+            ["exempt", "role", _] => {
+                let role_id = args.role(2)?;
+                update_config(context, target().await?, move |x| {
+                    toggle_id(&mut x.exempt_roles, role_id, remove)
+                })
+                .await?;
+                "meslimit.updated"
+            }
+            ["archive", _] => {
+                let on = switch(args, 1)?;
+                update_config(context, target().await?, move |x| x.archive = on).await?;
+                if on {
+                    "meslimit.archive_enabled"
+                } else {
+                    "meslimit.updated"
                 }
-                _ => "不正なコマンドです。",
             }
+            ["threads", _] => {
+                let on = switch(args, 1)?;
+                update_config(context, target().await?, move |x| x.threads = on).await?;
+                "meslimit.updated"
+            }
+            ["status"] => return status(context, message).await,
+            ["dryrun", _] => {
+                let size = args.integer(1, 1..=10000)? as i32;
+                return dry_run(context, message, size).await;
+            }
+            ["export", _, _] => {
+                return export(context, message, date(args, 1)?, date(args, 2)?).await
+            }
+            _ => return Err(Error::Usage),
+        };
+
+        let reply = context.text(message.guild_id, Text::new(reply)).await?;
+        context
+            .callbacks
+            .send_message(message.reply(&reply))
+            .await?;
+
+        // Paging through a long history would hold up every channel that
+        // shares this worker, so the backfill runs on its own.
+        if let Some(guard) = backfill_guard {
+            let (context, message) = (context.clone(), message.clone());
+            let span = info_span!("backfill", channel_id = message.channel_id);
+            tokio::spawn(
+                async move {
+                    if let Err(error) = backfill(&context, &message).await {
+                        error!(%error, "backfill failed");
+                    }
+                    drop(guard);
+                }
+                .instrument(span),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_snowflake() {
+        use super::{snowflake_at, snowflake_before, snowflake_time, DISCORD_EPOCH_MS};
+        use std::time::Duration;
+        assert_eq!(snowflake_at(0), 0);
+        assert_eq!(snowflake_at(DISCORD_EPOCH_MS), 0);
+        assert_eq!(snowflake_at(DISCORD_EPOCH_MS + 1), 1 << 22);
+        let ms = 1_600_000_000_000;
+        assert_eq!(
+            snowflake_time(snowflake_at(ms)).timestamp_millis(),
+            ms as i64
+        );
+        // A message sent a millisecond earlier has a smaller id whatever its
+        // low bits.
+        assert!(snowflake_at(ms - 1) | ((1 << 22) - 1) < snowflake_at(ms));
+        assert!(snowflake_before(Duration::from_secs(3600)) < snowflake_before(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_bulk_batches() {
+        use super::{bulk_batches, PendingDeletion, BULK_DELETE_MAX_COUNT};
+        let pending = |message_id| PendingDeletion {
+            message_id,
+            channel_id: 1,
+            attempts: 0,
+            next_attempt: 0,
+            last_error: None,
+            dead: false,
+            archive: false,
+        };
+        let split = |old: i64, recent: i64| {
+            let messages = (0..old).chain(1000..1000 + recent).map(pending).collect();
+            let (batches, single) = bulk_batches(messages, 1000);
+            let sizes: Vec<_> = batches.iter().map(Vec::len).collect();
+            let single: Vec<_> = single.iter().map(|x| x.message_id).collect();
+            (sizes, single)
+        };
+
+        assert_eq!(split(2, 0), (vec![], vec![0, 1]));
+        assert_eq!(split(0, 1), (vec![], vec![1000]));
+        assert_eq!(split(1, 2), (vec![2], vec![0]));
+        assert_eq!(
+            split(0, 203),
+            (
+                vec![BULK_DELETE_MAX_COUNT, BULK_DELETE_MAX_COUNT, 3],
+                vec![]
+            )
+        );
+        // A single leftover can't be bulk deleted on its own.
+        assert_eq!(
+            split(1, 201),
+            (
+                vec![BULK_DELETE_MAX_COUNT, BULK_DELETE_MAX_COUNT],
+                vec![0, 1200]
+            )
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        use super::{retry_delay, DELETE_RETRY_BASE, DELETE_RETRY_MAX};
+        assert_eq!(retry_delay(-1), DELETE_RETRY_BASE);
+        assert_eq!(retry_delay(0), DELETE_RETRY_BASE);
+        assert_eq!(retry_delay(3), DELETE_RETRY_BASE * 8);
+        assert_eq!(retry_delay(12), DELETE_RETRY_MAX);
+        assert_eq!(retry_delay(i32::MAX), DELETE_RETRY_MAX);
+    }
+
+    #[test]
+    fn test_count_expired() {
+        use super::{count_expired, History, HistoryWindowConfig};
+        let history = |message_id, author_id, pinned| History {
+            message_id,
+            channel_id: 1,
+            author_id,
+            author_roles: vec![10],
+            has_attachments: false,
+            pinned,
+            guild_id: 1,
+        };
+        // Newest first.
+        let records = vec![
+            history(6, 1, false),
+            history(5, 2, false),
+            history(4, 1, true),
+            history(3, 1, false),
+            history(2, 1, false),
+            history(1, 1, false),
+        ];
+        let window = |window_size| HistoryWindowConfig {
+            window_size: Some(window_size),
+            ..HistoryWindowConfig::default()
+        };
+
+        assert_eq!(count_expired(&records, &window(2), None), 4);
+        assert_eq!(count_expired(&records, &window(10), Some(3)), 2);
+        assert_eq!(
+            count_expired(&records, &HistoryWindowConfig::default(), None),
+            0
+        );
+        let exempt = HistoryWindowConfig {
+            exempt_pinned: true,
+            exempt_users: vec![2],
+            ..window(2)
+        };
+        assert_eq!(count_expired(&records, &exempt, None), 2);
+        let exempt_role = HistoryWindowConfig {
+            exempt_roles: vec![10],
+            ..window(0)
+        };
+        assert_eq!(count_expired(&records, &exempt_role, None), 0);
+    }
+
+    #[test]
+    fn test_resolve() {
+        use super::{candidates, needs_channel, resolve, HistoryWindowConfig};
+        let config = |guild_id, channel_id, category, threads| HistoryWindowConfig {
+            guild_id,
+            channel_id,
+            category,
+            threads,
+            ..HistoryWindowConfig::default()
         };
+        let channel_ids = |configs: Vec<HistoryWindowConfig>| -> Vec<i64> {
+            configs.iter().map(|x| x.channel_id).collect()
+        };
+        let configs = vec![
+            config(1, 0, false, false),
+            config(1, 5, true, false),
+            config(1, 7, false, false),
+            config(1, 8, false, true),
+            config(2, 0, false, false),
+        ];
+
+        assert_eq!(channel_ids(candidates(&configs, 1, 7)), vec![0, 5, 7, 8]);
+        assert_eq!(channel_ids(candidates(&configs, 1, 9)), vec![0, 5, 8]);
+        assert_eq!(channel_ids(candidates(&configs, 0, 7)), vec![7]);
+
+        assert!(!needs_channel(&[], 9));
+        assert!(!needs_channel(&candidates(&configs, 1, 7), 7));
+        assert!(needs_channel(&candidates(&configs, 1, 9), 9));
+
+        let resolved = |channel_id, parent_id| {
+            resolve(candidates(&configs, 1, channel_id), channel_id, parent_id)
+                .map(|x| x.channel_id)
+        };
+        // The channel's own row, then its category's, then the guild default.
+        assert_eq!(resolved(7, Some(5)), Some(7));
+        assert_eq!(resolved(9, Some(5)), Some(5));
+        assert_eq!(resolved(9, Some(6)), Some(0));
+        // A channel row is not a category row.
+        assert_eq!(resolved(9, Some(7)), Some(0));
+        assert_eq!(resolve(Vec::new(), 9, None).map(|x| x.channel_id), None);
+
+        // A thread follows its parent only when the parent opts threads in.
+        let thread = |parent_id| {
+            resolve(candidates(&configs, 1, 100), parent_id, None)
+                .filter(|x| x.threads)
+                .map(|x| x.channel_id)
+        };
+        assert_eq!(thread(8), Some(8));
+        assert_eq!(thread(7), None);
+    }
+
+    #[test]
+    fn test_scope_key() {
+        use super::{scope_key, HistoryWindowConfig};
+        let config = |guild_id, channel_id, category, threads| HistoryWindowConfig {
+            guild_id,
+            channel_id,
+            category,
+            threads,
+            ..HistoryWindowConfig::default()
+        };
+        assert_eq!(
+            scope_key(&config(1, 7, false, false), 7),
+            "meslimit.scope.channel"
+        );
+        assert_eq!(
+            scope_key(&config(1, 5, true, false), 7),
+            "meslimit.scope.category"
+        );
+        assert_eq!(
+            scope_key(&config(1, 8, false, true), 7),
+            "meslimit.scope.parent"
+        );
+        assert_eq!(
+            scope_key(&config(1, 0, false, false), 7),
+            "meslimit.scope.guild"
+        );
+    }
 
-        context.callbacks.send_message(message.reply(reply)).await
+    #[test]
+    fn test_day_start() {
+        use super::day_start;
+        use chrono::NaiveDate;
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(day_start(day(2014, 12, 31)), None);
+        assert_eq!(day_start(day(2015, 1, 1)), Some(0));
+        assert_eq!(
+            day_start(day(2015, 1, 2)),
+            Some((24 * 60 * 60 * 1000) << 22)
+        );
+        assert!(day_start(day(2084, 9, 6)).is_some());
+        assert_eq!(day_start(day(2084, 9, 7)), None);
+        assert_eq!(day_start(day(262_143, 12, 31)), None);
     }
 }
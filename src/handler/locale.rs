@@ -0,0 +1,96 @@
+use crate::bot::{
+    Args, CommandHandler, CommandOption, CommandOptionKind, Context, IncomingMessage, Locale,
+    SlashCommand, Text,
+};
+use crate::error::{Error, Result};
+use crate::models::GuildConfig;
+use crate::schema::guild_config::dsl as guild_config;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use twilight_model::guild::Permissions;
+
+pub struct LocaleConfigurator;
+
+#[async_trait]
+impl CommandHandler for LocaleConfigurator {
+    fn name(&self) -> &'static str {
+        "locale"
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        &["set <ja|en>", "reset"]
+    }
+
+    fn description(&self) -> &'static str {
+        "locale.description"
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        Some(SlashCommand::new(
+            self.name(),
+            self.description(),
+            vec![
+                CommandOption::sub_command(
+                    "set",
+                    "locale.slash.set",
+                    vec![CommandOption::value(
+                        "locale",
+                        "locale.slash.locale",
+                        CommandOptionKind::String,
+                        true,
+                    )],
+                ),
+                CommandOption::sub_command("reset", "locale.slash.reset", vec![]),
+            ],
+        ))
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
+        (_, message): &IncomingMessage,
+        context: &Context,
+    ) -> Result<()> {
+        let guild_id = message
+            .guild_id
+            .ok_or_else(|| Error::UserInput(Text::new("command.guild_only")))?;
+
+        let (locale, reply) = match args.as_strs()[..] {
+            ["set", code] => {
+                let locale = Locale::from_code(code).ok_or_else(|| {
+                    let codes: Vec<_> = Locale::ALL.iter().map(|x| x.code()).collect();
+                    Error::UserInput(Text::new("locale.unknown").param("locales", codes.join(", ")))
+                })?;
+                (Some(locale), "locale.changed")
+            }
+            ["reset"] => (None, "locale.reset"),
+            _ => return Err(Error::Usage),
+        };
+
+        let code = locale.map(|x| x.code().to_string());
+        context
+            .db
+            .run(move |conn| {
+                diesel::insert_into(guild_config::guild_config)
+                    .values(GuildConfig {
+                        guild_id: guild_id as i64,
+                        prefix: None,
+                        locale: code.clone(),
+                    })
+                    .on_conflict(guild_config::guild_id)
+                    .do_update()
+                    .set(guild_config::locale.eq(code))
+                    .execute(conn)
+            })
+            .await?;
+
+        context.locales.forget(guild_id);
+
+        let reply = context.text(message.guild_id, Text::new(reply)).await?;
+        context.callbacks.send_message(message.reply(&reply)).await
+    }
+}
@@ -1,3 +1,6 @@
+pub mod features;
 pub mod history_window;
+pub mod locale;
 pub mod ping;
+pub mod prefix;
 pub mod quote;
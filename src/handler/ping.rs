@@ -1,9 +1,40 @@
-use crate::bot::{IncomingMessage, Message};
+use crate::bot::{Args, CommandHandler, Context, IncomingMessage, Text};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
 
-pub fn ping((_, message): &IncomingMessage) -> Option<Message> {
-    if message.content == "ping?" {
-        Some(message.reply("pong!"))
-    } else {
-        None
+pub struct Ping;
+
+#[async_trait]
+impl CommandHandler for Ping {
+    fn name(&self) -> &'static str {
+        "ping"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["ping?"]
+    }
+
+    fn description(&self) -> &'static str {
+        "ping.description"
+    }
+
+    fn feature(&self) -> Option<&'static str> {
+        Some("ping")
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
+        (_, message): &IncomingMessage,
+        context: &Context,
+    ) -> Result<()> {
+        if !args.is_empty() {
+            return Err(Error::Usage);
+        }
+
+        let reply = context
+            .text(message.guild_id, Text::new("ping.pong"))
+            .await?;
+        context.callbacks.send_message(message.reply(&reply)).await
     }
 }
@@ -0,0 +1,96 @@
+use crate::bot::{
+    Args, CommandHandler, CommandOption, CommandOptionKind, Context, IncomingMessage, SlashCommand,
+    Text,
+};
+use crate::error::{Error, Result};
+use crate::models::GuildConfig;
+use crate::schema::guild_config::dsl as guild_config;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use twilight_model::guild::Permissions;
+
+pub struct PrefixConfigurator;
+
+#[async_trait]
+impl CommandHandler for PrefixConfigurator {
+    fn name(&self) -> &'static str {
+        "prefix"
+    }
+
+    fn usage(&self) -> &'static [&'static str] {
+        &["set <prefix>", "reset"]
+    }
+
+    fn description(&self) -> &'static str {
+        "prefix.description"
+    }
+
+    fn required_permissions(&self) -> Permissions {
+        Permissions::MANAGE_GUILD
+    }
+
+    fn slash_command(&self) -> Option<SlashCommand> {
+        Some(SlashCommand::new(
+            self.name(),
+            self.description(),
+            vec![
+                CommandOption::sub_command(
+                    "set",
+                    "prefix.slash.set",
+                    vec![CommandOption::value(
+                        "prefix",
+                        "prefix.slash.prefix",
+                        CommandOptionKind::String,
+                        true,
+                    )],
+                ),
+                CommandOption::sub_command("reset", "prefix.slash.reset", vec![]),
+            ],
+        ))
+    }
+
+    async fn handler(
+        &self,
+        args: &Args,
+        (_, message): &IncomingMessage,
+        context: &Context,
+    ) -> Result<()> {
+        let guild_id = message
+            .guild_id
+            .ok_or_else(|| Error::UserInput(Text::new("command.guild_only")))?;
+
+        let (prefix, reply) = match args.as_strs()[..] {
+            ["set", prefix] => {
+                if !(1..=16).contains(&prefix.chars().count()) {
+                    return Err(Error::UserInput(
+                        Text::new("prefix.length").param("min", 1).param("max", 16),
+                    ));
+                }
+                (Some(prefix.to_string()), "prefix.changed")
+            }
+            ["reset"] => (None, "prefix.reset"),
+            _ => return Err(Error::Usage),
+        };
+
+        context
+            .db
+            .run(move |conn| {
+                diesel::insert_into(guild_config::guild_config)
+                    .values(GuildConfig {
+                        guild_id: guild_id as i64,
+                        prefix: prefix.clone(),
+                        locale: None,
+                    })
+                    .on_conflict(guild_config::guild_id)
+                    .do_update()
+                    .set(guild_config::prefix.eq(prefix))
+                    .execute(conn)
+            })
+            .await?;
+
+        context.prefix.forget(guild_id);
+
+        let reply = context.text(message.guild_id, Text::new(reply)).await?;
+        context.callbacks.send_message(message.reply(&reply)).await
+    }
+}
@@ -1,58 +1,165 @@
-use crate::bot::{Context, Embed, IncomingMessage, MessageHandler};
+use crate::bot::{Attachment, ChannelKind, Context, Embed, IncomingMessage, MessageHandler, User};
+use crate::error::Result;
+use crate::metrics::QUOTES_PRODUCED;
 use async_trait::async_trait;
-use chrono::Local;
 use once_cell::sync::OnceCell;
 use regex::{Match, Regex};
-use std::error::Error;
+
+// Discord groups at most four images into a gallery and allows ten embeds per
+// message.
+const GALLERY_SIZE: usize = 4;
+const EMBEDS_PER_MESSAGE: usize = 10;
+const REPLY_PREVIEW_LENGTH: usize = 100;
+
+fn jump_url(guild_id: Option<u64>, channel_id: u64, message_id: u64) -> String {
+    let guild = guild_id.map_or_else(|| "@me".to_string(), |x| x.to_string());
+    format!(
+        "https://discord.com/channels/{}/{}/{}",
+        guild, channel_id, message_id
+    )
+}
+
+fn avatar_url(author: &User) -> String {
+    author
+        .avatar
+        .as_ref()
+        .map(|avatar| {
+            format!(
+                "https://cdn.discordapp.com/avatars/{}/{}.png",
+                author.id, avatar
+            )
+        })
+        .unwrap_or_else(|| "https://cdn.discordapp.com/embed/avatars/0.png".to_string())
+}
+
+fn reply_preview(guild_id: Option<u64>, (message_id, message): &IncomingMessage) -> String {
+    let content = message.content.replace('\n', " ");
+    let mut preview: String = content.chars().take(REPLY_PREVIEW_LENGTH).collect();
+    if preview.len() < content.len() {
+        preview.push('…');
+    }
+
+    format!(
+        "> [↪]({}) **{}** {}\n",
+        jump_url(guild_id, message.channel_id, *message_id),
+        message.author.name,
+        preview
+    )
+}
+
+fn attachment_link(attachment: &Attachment) -> String {
+    let link = format!("[{}]({})", attachment.filename, attachment.url);
+    if attachment.is_spoiler() {
+        format!("||{}||", link)
+    } else {
+        link
+    }
+}
+
+// Splits attachments into the images shown in the gallery and the files that
+// are linked instead. Spoilers are never shown inline; images past the gallery
+// are linked like any other file.
+fn split_gallery(attachments: Vec<Attachment>) -> (Vec<Attachment>, Vec<Attachment>) {
+    let (mut images, mut files): (Vec<_>, Vec<_>) = attachments
+        .into_iter()
+        .partition(|x| x.is_image() && !x.is_spoiler());
+    let overflow = images.split_off(images.len().min(GALLERY_SIZE));
+    files.extend(overflow);
+
+    (images, files)
+}
+
+// Packs each quote's embeds into as few messages as possible without splitting
+// a quote, since a gallery only shows when its embeds arrive together.
+fn pack_quotes(quotes: Vec<Vec<Embed>>) -> Vec<Vec<Embed>> {
+    let mut messages: Vec<Vec<Embed>> = Vec::new();
+    for quote in quotes {
+        match messages.last_mut() {
+            Some(last) if last.len() + quote.len() <= EMBEDS_PER_MESSAGE => last.extend(quote),
+            _ => messages.push(quote),
+        }
+    }
+
+    messages
+}
 
 async fn message_to_quote(
     context: &Context,
     channel_id: Option<u64>,
     message_id: Option<u64>,
-) -> Option<(Embed, Vec<Embed>)> {
-    let channel = context.callbacks.fetch_channel(channel_id?).await?;
+) -> Option<(Vec<Embed>, Vec<Embed>)> {
+    let (channel_id, message_id) = (channel_id?, message_id?);
+    let channel = context.channel(channel_id).await.ok().flatten()?;
     let message = context
         .callbacks
-        .fetch_message(channel_id?, message_id?)
+        .fetch_message(channel_id, message_id)
         .await?;
 
-    let author = message.author;
-    let author_id = author.id;
-    let avatar_url = author
-        .avatar
-        .map(|avatar| {
-            format!(
-                "https://cdn.discordapp.com/avatars/{}/{}.png",
-                author_id, avatar
-            )
-        })
-        .unwrap_or_else(|| "https://cdn.discordapp.com/embed/avatars/0.png".to_string());
-    let now = Local::now().format("%Y/%m/%d %H:%M:%S").to_string();
+    // Threads are shown under the channel they were started in.
+    let location = match channel.parent_id {
+        Some(parent_id) if channel.kind == ChannelKind::Thread => {
+            match context.channel(parent_id).await {
+                Ok(Some(parent)) => format!("#{} › {}", parent.name, channel.name),
+                _ => format!("#{}", channel.name),
+            }
+        }
+        _ => format!("#{}", channel.name),
+    };
 
-    let mut embed = Embed::new()
-        .author_name(author.name)
-        .avatar_url(avatar_url)
-        .description(message.content)
-        .footer(format!("#{} - {}", channel.name, now));
-
-    if let Some(attachment) = message.attachments.into_iter().next() {
-        if !attachment.filename.starts_with("SPOILER_") {
-            embed = embed.image(attachment.url);
+    let color = match channel.guild_id {
+        Some(guild_id) if !message.author.webhook => {
+            context
+                .callbacks
+                .fetch_member_color(guild_id, message.author.id)
+                .await
         }
+        _ => None,
+    };
+
+    let url = jump_url(channel.guild_id, channel_id, message_id);
+    let mut description = message
+        .reply_to
+        .as_ref()
+        .map(|reply| reply_preview(channel.guild_id, reply))
+        .unwrap_or_default();
+    description.push_str(&message.content);
+
+    let (images, files) = split_gallery(message.attachments);
+    for attachment in files {
+        description.push('\n');
+        description.push_str(&attachment_link(&attachment));
     }
 
-    Some((embed, message.embeds))
+    let mut embed = Embed::new()
+        .author_name(message.author.name.clone())
+        .avatar_url(avatar_url(&message.author))
+        .description(description)
+        .footer(location)
+        .timestamp(message.timestamp)
+        .url(url.clone());
+    if let Some(color) = color {
+        embed = embed.color(color);
+    }
+    let mut gallery = images.into_iter();
+    if let Some(image) = gallery.next() {
+        embed = embed.image(image.url);
+    }
+
+    let mut quotes = vec![embed];
+    quotes.extend(gallery.map(|image| Embed::new().url(url.clone()).image(image.url)));
+
+    Some((quotes, message.embeds))
 }
 
 pub struct Quote;
 
 #[async_trait]
 impl MessageHandler for Quote {
-    async fn on_message(
-        &mut self,
-        (_, message): &IncomingMessage,
-        context: &Context,
-    ) -> Result<(), Box<dyn Error>> {
+    fn feature(&self) -> Option<&'static str> {
+        Some("quote")
+    }
+
+    async fn on_message(&self, (_, message): &IncomingMessage, context: &Context) -> Result<()> {
         static REGEX: OnceCell<Regex> = OnceCell::new();
 
         let regex = REGEX.get_or_init(|| {
@@ -64,6 +171,7 @@ impl MessageHandler for Quote {
 
         let mut quotes = Vec::new();
         let mut quoted_embeds = Vec::new();
+        let mut produced = 0;
 
         for capture in regex.captures_iter(&message.content) {
             let parse = |x: Option<Match<'_>>| x.and_then(|x| x.as_str().parse().ok());
@@ -71,8 +179,9 @@ impl MessageHandler for Quote {
             let message_id = parse(capture.name("messageId"));
 
             if let Some((quote, mut embeds)) =
-                message_to_quote(&context, channel_id, message_id).await
+                message_to_quote(context, channel_id, message_id).await
             {
+                produced += 1;
                 quotes.push(quote);
                 quoted_embeds.append(&mut embeds);
             }
@@ -82,14 +191,78 @@ impl MessageHandler for Quote {
             return Ok(());
         }
 
-        let reply = message.reply("").set_embeds(quotes);
-        context.callbacks.send_message(reply).await?;
+        QUOTES_PRODUCED.inc_by(produced);
 
-        if !quoted_embeds.is_empty() {
-            let reply = message.reply("embeds:").set_embeds(quoted_embeds);
+        for embeds in pack_quotes(quotes) {
+            let reply = message.reply("").set_embeds(embeds);
+            context.callbacks.send_message(reply).await?;
+        }
+
+        for chunk in quoted_embeds.chunks(EMBEDS_PER_MESSAGE) {
+            let reply = message.reply("embeds:").set_embeds(chunk.to_vec());
             context.callbacks.send_message(reply).await?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_split_gallery() {
+        use super::{split_gallery, Attachment};
+        let attachment = |filename: &str, content_type: &str| Attachment {
+            filename: filename.to_string(),
+            url: String::new(),
+            content_type: Some(content_type.to_string()),
+        };
+        let names = |x: Vec<Attachment>| {
+            x.into_iter()
+                .map(|x| x.filename)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let (images, files) = split_gallery(vec![
+            attachment("a.png", "image/png"),
+            attachment("b.txt", "text/plain"),
+            attachment("SPOILER_c.png", "image/png"),
+            attachment("d.jpg", "image/jpeg"),
+        ]);
+        assert_eq!(names(images), "a.png,d.jpg");
+        assert_eq!(names(files), "b.txt,SPOILER_c.png");
+
+        let (images, files) = split_gallery(
+            (1..=6)
+                .map(|x| attachment(&format!("{}.png", x), "image/png"))
+                .collect(),
+        );
+        assert_eq!(names(images), "1.png,2.png,3.png,4.png");
+        assert_eq!(names(files), "5.png,6.png");
+    }
+
+    #[test]
+    fn test_pack_quotes() {
+        use super::{pack_quotes, Embed};
+        let quote = |size: usize| vec![Embed::new(); size];
+        let sizes = |x: Vec<Vec<Embed>>| x.iter().map(Vec::len).collect::<Vec<_>>();
+
+        assert_eq!(sizes(pack_quotes(vec![quote(1), quote(4), quote(4)])), [9]);
+        assert_eq!(
+            sizes(pack_quotes(vec![quote(4), quote(4), quote(4)])),
+            [8, 4]
+        );
+        assert_eq!(
+            sizes(pack_quotes(vec![
+                quote(1),
+                quote(4),
+                quote(4),
+                quote(2),
+                quote(1)
+            ])),
+            [9, 3]
+        );
+        assert!(pack_quotes(Vec::new()).is_empty());
+    }
+}
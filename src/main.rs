@@ -2,50 +2,153 @@
 extern crate diesel;
 
 mod bot;
+mod error;
 mod handler;
+mod metrics;
 mod models;
 mod schema;
 
 use async_trait::async_trait;
-use bot::{Bot, Channel, FnMessageHandler, Message, ResponseCallbacks};
-use diesel::{Connection, PgConnection};
+use bot::{
+    Bot, Channel, Database, Dispatcher, IncomingMessage, Locale, Locales, Message, Prefix,
+    ResponseCallbacks, SlashCommand,
+};
+use error::{Error as BotError, Result};
 use futures::StreamExt;
 use handler::{
-    history_window::{HistoryWindow, HistoryWindowConfigurator},
-    ping::ping,
+    features::FeatureConfigurator,
+    history_window::{self, HistoryWindow, HistoryWindowConfigurator},
+    locale::LocaleConfigurator,
+    ping::Ping,
+    prefix::PrefixConfigurator,
     quote::Quote,
 };
-use std::{convert::TryFrom, env, error::Error, num::NonZeroU64};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use std::{convert::TryFrom, env, error::Error, num::NonZeroU64, sync::Arc, time::Duration};
+use tracing_subscriber::EnvFilter;
 use twilight_gateway::cluster::{Cluster, ShardScheme};
-use twilight_http::Client;
-use twilight_model::gateway::Intents;
+use twilight_http::request::{AttachmentFile, Request};
+use twilight_http::response::marker::ListBody;
+use twilight_http::routing::Route;
+use twilight_http::{error::ErrorType, Client};
+use twilight_model::application::callback::{CallbackData, InteractionResponse};
+use twilight_model::application::command::Command;
+use twilight_model::guild::Permissions;
+
+fn is_not_found(error: &twilight_http::Error) -> bool {
+    matches!(error.kind(), ErrorType::Response { status, .. } if status.raw() == 404)
+}
+
+// Role colors rarely change, and one message can quote the same author many
+// times.
+const MEMBER_COLOR_TTL: Duration = Duration::from_secs(10 * 60);
+
+type MemberColors = HashMap<(u64, u64), (Instant, Option<u32>)>;
 
 struct Callbacks {
-    http: Client,
+    http: Arc<Client>,
+    member_colors: Mutex<MemberColors>,
+}
+
+impl Callbacks {
+    async fn member_color(&self, guild_id: u64, user_id: u64) -> Result<Option<u32>> {
+        let (guild_id, user_id) = match (NonZeroU64::new(guild_id), NonZeroU64::new(user_id)) {
+            (Some(guild_id), Some(user_id)) => (guild_id.into(), user_id.into()),
+            _ => return Ok(None),
+        };
+
+        let member = self
+            .http
+            .guild_member(guild_id, user_id)
+            .exec()
+            .await?
+            .model()
+            .await?;
+        let roles = self.http.roles(guild_id).exec().await?.models().await?;
+
+        Ok(roles
+            .into_iter()
+            .filter(|role| role.color != 0 && member.roles.contains(&role.id))
+            .max_by_key(|role| role.position)
+            .map(|role| role.color))
+    }
+}
+
+async fn register_commands(http: &Client, commands: Vec<SlashCommand>) -> Result<()> {
+    let application = http
+        .current_user_application()
+        .exec()
+        .await?
+        .model()
+        .await?;
+    http.set_application_id(application.id);
+
+    // Sent as JSON to carry the description localizations.
+    let commands: Vec<_> = commands.iter().map(SlashCommand::to_json).collect();
+    let request = Request::builder(&Route::SetGlobalCommands {
+        application_id: application.id.get(),
+    })
+    .json(&commands)?
+    .build();
+    http.request::<ListBody<Command>>(request).await?;
+
+    Ok(())
 }
 
 #[async_trait]
 impl ResponseCallbacks for Callbacks {
-    async fn send_message(&self, message: Message) -> Result<(), Box<dyn Error>> {
+    async fn send_message(&self, message: Message) -> Result<()> {
         let embeds: Vec<_> = message.embeds.into_iter().map(Into::into).collect();
+        let files: Vec<_> = message
+            .files
+            .iter()
+            .map(|(name, data)| AttachmentFile::from_bytes(name, data))
+            .collect();
 
         self.http
             .create_message(NonZeroU64::try_from(message.channel_id).unwrap().into())
             .embeds(&embeds)?
             .content(&message.content)?
+            .attach(&files)
             .exec()
             .await?;
 
         Ok(())
     }
 
-    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<(), Box<dyn Error>> {
-        self.http
+    async fn delete_message(&self, channel_id: u64, message_id: u64) -> Result<()> {
+        let result = self
+            .http
             .delete_message(
                 NonZeroU64::try_from(channel_id).unwrap().into(),
                 NonZeroU64::try_from(message_id).unwrap().into(),
             )
             .exec()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // Someone else deleted it first, which is all the caller wanted.
+            Err(error) if is_not_found(&error) => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn delete_messages(&self, channel_id: u64, message_ids: &[u64]) -> Result<()> {
+        let message_ids: Vec<_> = message_ids
+            .iter()
+            .map(|x| NonZeroU64::try_from(*x).unwrap().into())
+            .collect();
+
+        self.http
+            .delete_messages(
+                NonZeroU64::try_from(channel_id).unwrap().into(),
+                &message_ids,
+            )
+            .exec()
             .await?;
 
         Ok(())
@@ -66,25 +169,240 @@ impl ResponseCallbacks for Callbacks {
             .map(|x| x.into())
     }
 
-    async fn fetch_channel(&self, channel_id: u64) -> Option<Channel> {
+    async fn defer_interaction(&self, id: u64, token: &str) -> Result<()> {
+        let response = InteractionResponse::DeferredChannelMessageWithSource(CallbackData {
+            allowed_mentions: None,
+            components: None,
+            content: None,
+            embeds: Vec::new(),
+            flags: None,
+            tts: None,
+        });
+
+        self.http
+            .interaction_callback(NonZeroU64::try_from(id).unwrap().into(), token, &response)
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn followup_interaction(&self, token: &str, message: Message) -> Result<()> {
+        let embeds: Vec<_> = message.embeds.into_iter().map(Into::into).collect();
+        let files: Vec<_> = message
+            .files
+            .iter()
+            .map(|(name, data)| AttachmentFile::from_bytes(name, data))
+            .collect();
+
         self.http
-            .channel(NonZeroU64::try_from(channel_id).unwrap().into())
+            .create_followup_message(token)?
+            .embeds(&embeds)
+            .content(&message.content)
+            .attach(&files)
+            .exec()
+            .await?;
+
+        Ok(())
+    }
+
+    // Guild-level permissions: the owner holds everything, everyone else gets
+    // the union of @everyone and their roles.
+    async fn fetch_permissions(&self, guild_id: u64, user_id: u64) -> Option<Permissions> {
+        let guild_id = NonZeroU64::try_from(guild_id).ok()?.into();
+        let user_id = NonZeroU64::try_from(user_id).ok()?.into();
+
+        let guild = self
+            .http
+            .guild(guild_id)
             .exec()
             .await
             .ok()?
             .model()
             .await
-            .ok()
-            .map(|x| x.into())
+            .ok()?;
+        if guild.owner_id == user_id {
+            return Some(Permissions::all());
+        }
+
+        let member = self
+            .http
+            .guild_member(guild_id, user_id)
+            .exec()
+            .await
+            .ok()?
+            .model()
+            .await
+            .ok()?;
+
+        let permissions = guild
+            .roles
+            .iter()
+            .filter(|role| role.id.0 == guild_id.0 || member.roles.contains(&role.id))
+            .fold(Permissions::empty(), |acc, role| acc | role.permissions);
+
+        Some(permissions)
+    }
+
+    async fn fetch_member_color(&self, guild_id: u64, user_id: u64) -> Option<u32> {
+        let key = (guild_id, user_id);
+        if let Some((since, color)) = self.member_colors.lock().unwrap().get(&key) {
+            if since.elapsed() < MEMBER_COLOR_TTL {
+                return *color;
+            }
+        }
+
+        // Failed lookups are asked for again next time.
+        let color = self.member_color(guild_id, user_id).await.ok()?;
+        let mut member_colors = self.member_colors.lock().unwrap();
+        member_colors.retain(|_, (since, _)| since.elapsed() < MEMBER_COLOR_TTL);
+        member_colors.insert(key, (Instant::now(), color));
+
+        color
+    }
+
+    async fn fetch_history(
+        &self,
+        channel_id: u64,
+        before: Option<u64>,
+        limit: u64,
+    ) -> Result<Vec<IncomingMessage>> {
+        let request = self
+            .http
+            .channel_messages(NonZeroU64::try_from(channel_id).unwrap().into());
+
+        let response = match before {
+            Some(before) => {
+                request
+                    .before(NonZeroU64::try_from(before).unwrap().into())
+                    .limit(limit)?
+                    .exec()
+                    .await?
+            }
+            None => request.limit(limit)?.exec().await?,
+        };
+
+        let messages = response
+            .models()
+            .await?
+            .into_iter()
+            .map(|x| (x.id.0.into(), x.into()))
+            .collect();
+
+        Ok(messages)
+    }
+
+    // Fetched as raw JSON so forum channels, which twilight can't parse, are
+    // found too.
+    async fn fetch_channel(&self, channel_id: u64) -> Result<Option<Channel>> {
+        let request = Request::from_route(&Route::GetChannel { channel_id });
+        let response = match self.http.request::<Value>(request).await {
+            Ok(response) => response,
+            Err(error) if is_not_found(&error) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let channel = Channel::from_json(response.model().await?).ok_or_else(|| {
+            BotError::Http(format!("channel {} has an unknown type", channel_id).into())
+        })?;
+
+        Ok(Some(channel))
+    }
+}
+
+fn init_logging() {
+    let filter =
+        EnvFilter::try_from_env("PETROLEUM_LOG").unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    if env::var("PETROLEUM_LOG_FORMAT").is_ok_and(|x| x == "json") {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    init_logging();
+
     let token = env::var("PETROLEUM_TOKEN")?;
     let database_url = std::env::var("DATABASE_URL")?;
+    let workers = env::var("PETROLEUM_WORKERS")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(4);
+    let pool_size = env::var("PETROLEUM_DB_POOL_SIZE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(8);
+    let sweep_interval = env::var("PETROLEUM_SWEEP_INTERVAL")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(60);
+    let prefix = env::var("PETROLEUM_PREFIX").unwrap_or_else(|_| "!".to_string());
+    let mention_prefix = env::var("PETROLEUM_MENTION_PREFIX").map_or(true, |x| x != "false");
+    let locale = env::var("PETROLEUM_LOCALE")
+        .ok()
+        .and_then(|x| Locale::from_code(&x))
+        .unwrap_or_default();
+    let owners = env::var("PETROLEUM_OWNERS")
+        .map(|x| x.split(',').filter_map(|x| x.trim().parse().ok()).collect())
+        .unwrap_or_default();
+
+    if let Ok(addr) = env::var("PETROLEUM_METRICS_ADDR") {
+        let addr = addr.parse()?;
+        tokio::spawn(async move {
+            if let Err(error) = metrics::serve(addr).await {
+                tracing::error!(%error, "metrics endpoint stopped");
+            }
+        });
+    }
+
+    let http = Arc::new(Client::new(token.clone()));
+    let db = Database::connect(&database_url, pool_size)?;
+
+    let pending = history_window::reconcile_deletions(&db).await?;
+    if pending > 0 {
+        tracing::info!(pending, "resuming pending deletions");
+    }
+
+    let mut handler = Bot::new(
+        Callbacks {
+            http: http.clone(),
+            member_colors: Mutex::default(),
+        },
+        db,
+    );
 
-    let (cluster, mut events) = Cluster::builder(&token, Intents::GUILD_MESSAGES)
+    let mut prefix = Prefix::new(&prefix);
+    let current_user = http.current_user().exec().await?.model().await?;
+    handler.set_current_user(current_user.id.0.into());
+
+    if mention_prefix {
+        prefix = prefix.mention(current_user.id.0.into());
+    }
+    handler.set_prefix(prefix);
+    handler.set_locales(Locales::new(locale));
+    handler.set_owners(owners);
+
+    handler.on_message(Quote);
+    handler.on_message(HistoryWindow);
+    handler.on_message_update(HistoryWindow);
+    handler.on_message_delete(HistoryWindow);
+    handler.on_message_delete_bulk(HistoryWindow);
+    handler.on_channel_delete(HistoryWindow);
+    handler.on_timer(Duration::from_secs(sweep_interval), HistoryWindow);
+    handler.on_command(Ping);
+    handler.on_command(HistoryWindowConfigurator);
+    handler.on_command(PrefixConfigurator);
+    handler.on_command(LocaleConfigurator);
+    handler.on_command(FeatureConfigurator);
+
+    register_commands(&http, handler.slash_commands()).await?;
+
+    let (cluster, mut events) = Cluster::builder(&token, handler.intents())
         .shard_scheme(ShardScheme::Auto)
         .build()
         .await?;
@@ -93,18 +411,10 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         cluster.up().await;
     });
 
-    let http = Client::new(token);
-    let db_conn = PgConnection::establish(&database_url).unwrap();
-
-    let mut handler = Bot::new(Callbacks { http }, db_conn);
-
-    handler.on_message(Quote);
-    handler.on_message(FnMessageHandler(ping));
-    handler.on_message(HistoryWindow);
-    handler.on_message(HistoryWindowConfigurator);
+    let dispatcher = Dispatcher::new(handler, workers);
 
     while let Some((_, event)) = events.next().await {
-        handler.handle(event).await;
+        dispatcher.dispatch(event).await;
     }
 
     Ok(())
@@ -0,0 +1,149 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY.register(Box::new(collector.clone())).unwrap();
+    collector
+}
+
+pub static EVENTS_RECEIVED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("petroleum_events_received_total", "Gateway events received"),
+            &["kind"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static HANDLER_INVOCATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("petroleum_handler_invocations_total", "Handler invocations"),
+            &["handler"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static HANDLER_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "petroleum_handler_errors_total",
+                "Handler invocations that failed",
+            ),
+            &["handler"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static DISCORD_API_CALLS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("petroleum_discord_api_calls_total", "Discord API calls"),
+            &["call", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static DISCORD_API_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "petroleum_discord_api_duration_seconds",
+                "Discord API call latency",
+            ),
+            &["call"],
+        )
+        .unwrap(),
+    )
+});
+
+pub static HISTORY_WINDOW_DELETED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "petroleum_history_window_deleted_messages_total",
+            "Messages deleted by the history window",
+        )
+        .unwrap(),
+    )
+});
+
+pub static HISTORY_WINDOW_DEAD_LETTERED: Lazy<IntCounter> = Lazy::new(|| {
+    register(
+        IntCounter::new(
+            "petroleum_history_window_dead_lettered_messages_total",
+            "Messages the history window gave up deleting",
+        )
+        .unwrap(),
+    )
+});
+
+pub static QUOTES_PRODUCED: Lazy<IntCounter> =
+    Lazy::new(|| register(IntCounter::new("petroleum_quotes_total", "Quotes produced").unwrap()));
+
+fn render() -> Vec<u8> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .unwrap();
+    buffer
+}
+
+async fn respond(mut stream: TcpStream) -> io::Result<()> {
+    let mut request = [0; 1024];
+    let length = stream.read(&mut request).await?;
+
+    let response = if request[..length].starts_with(b"GET /metrics ") {
+        let body = render();
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            TextEncoder::new().format_type(),
+            body.len()
+        )
+        .into_bytes();
+        response.extend(body);
+        response
+    } else {
+        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+    };
+
+    stream.write_all(&response).await?;
+    stream.shutdown().await
+}
+
+pub async fn serve(addr: SocketAddr) -> io::Result<()> {
+    // Register every metric up front so they are exported before first use.
+    Lazy::force(&EVENTS_RECEIVED);
+    Lazy::force(&HANDLER_INVOCATIONS);
+    Lazy::force(&HANDLER_ERRORS);
+    Lazy::force(&DISCORD_API_CALLS);
+    Lazy::force(&DISCORD_API_DURATION);
+    Lazy::force(&HISTORY_WINDOW_DELETED);
+    Lazy::force(&HISTORY_WINDOW_DEAD_LETTERED);
+    Lazy::force(&QUOTES_PRODUCED);
+
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "serving metrics");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(error) = respond(stream).await {
+                warn!(%error, "failed to serve metrics");
+            }
+        });
+    }
+}
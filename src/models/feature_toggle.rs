@@ -0,0 +1,11 @@
+use crate::schema::feature_toggle;
+
+// A channel_id of 0 applies the toggle to the whole guild.
+#[derive(Clone, Queryable, Insertable)]
+#[table_name = "feature_toggle"]
+pub struct FeatureToggle {
+    pub guild_id: i64,
+    pub channel_id: i64,
+    pub feature: String,
+    pub enabled: bool,
+}
@@ -0,0 +1,9 @@
+use crate::schema::guild_config;
+
+#[derive(Queryable, Insertable)]
+#[table_name = "guild_config"]
+pub struct GuildConfig {
+    pub guild_id: i64,
+    pub prefix: Option<String>,
+    pub locale: Option<String>,
+}
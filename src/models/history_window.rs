@@ -1,15 +1,59 @@
-use crate::schema::{history, history_window_config};
+use crate::schema::{deletion_queue, history, history_window_config, message_archive};
+use serde_json::Value;
 
 #[derive(Queryable, Insertable)]
 #[table_name = "history"]
 pub struct History {
     pub message_id: i64,
     pub channel_id: i64,
+    pub author_id: i64,
+    pub author_roles: Vec<i64>,
+    pub has_attachments: bool,
+    pub pinned: bool,
+    pub guild_id: i64,
 }
 
-#[derive(Queryable, Insertable)]
+#[derive(Clone, Default, Queryable, Insertable)]
 #[table_name = "history_window_config"]
 pub struct HistoryWindowConfig {
+    // 0 for the guild default.
+    pub channel_id: i64,
+    pub window_size: Option<i32>,
+    // Seconds.
+    pub max_age: Option<i64>,
+    pub exempt_pinned: bool,
+    pub exempt_attachments: bool,
+    pub exempt_users: Vec<i64>,
+    pub exempt_roles: Vec<i64>,
+    pub archive: bool,
+    pub guild_id: i64,
+    pub category: bool,
+    pub threads: bool,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "deletion_queue"]
+pub struct PendingDeletion {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub attempts: i32,
+    // Unix seconds.
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+    pub dead: bool,
+    pub archive: bool,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "message_archive"]
+pub struct ArchivedMessage {
+    pub message_id: i64,
     pub channel_id: i64,
-    pub window_size: i32,
+    pub author_id: i64,
+    pub author_name: String,
+    pub content: String,
+    pub attachments: Value,
+    pub embeds: Value,
+    // False while the message is still in the channel.
+    pub trimmed: bool,
 }
@@ -1,3 +1,7 @@
+pub mod feature_toggle;
+pub mod guild_config;
 pub mod history_window;
 
+pub use feature_toggle::*;
+pub use guild_config::*;
 pub use history_window::*;
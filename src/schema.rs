@@ -1,20 +1,78 @@
+table! {
+    deletion_queue (message_id) {
+        message_id -> Int8,
+        channel_id -> Int8,
+        attempts -> Int4,
+        next_attempt -> Int8,
+        last_error -> Nullable<Text>,
+        dead -> Bool,
+        archive -> Bool,
+    }
+}
+
+table! {
+    feature_toggle (guild_id, channel_id, feature) {
+        guild_id -> Int8,
+        channel_id -> Int8,
+        feature -> Text,
+        enabled -> Bool,
+    }
+}
+
+table! {
+    guild_config (guild_id) {
+        guild_id -> Int8,
+        prefix -> Nullable<Text>,
+        locale -> Nullable<Text>,
+    }
+}
+
 table! {
     history (message_id) {
         message_id -> Int8,
         channel_id -> Int8,
+        author_id -> Int8,
+        author_roles -> Array<Int8>,
+        has_attachments -> Bool,
+        pinned -> Bool,
+        guild_id -> Int8,
     }
 }
 
 table! {
-    history_window_config (channel_id) {
+    history_window_config (guild_id, channel_id) {
         channel_id -> Int8,
-        window_size -> Int4,
+        window_size -> Nullable<Int4>,
+        max_age -> Nullable<Int8>,
+        exempt_pinned -> Bool,
+        exempt_attachments -> Bool,
+        exempt_users -> Array<Int8>,
+        exempt_roles -> Array<Int8>,
+        archive -> Bool,
+        guild_id -> Int8,
+        category -> Bool,
+        threads -> Bool,
     }
 }
 
-joinable!(history -> history_window_config (channel_id));
+table! {
+    message_archive (message_id) {
+        message_id -> Int8,
+        channel_id -> Int8,
+        author_id -> Int8,
+        author_name -> Text,
+        content -> Text,
+        attachments -> Jsonb,
+        embeds -> Jsonb,
+        trimmed -> Bool,
+    }
+}
 
 allow_tables_to_appear_in_same_query!(
+    deletion_queue,
+    feature_toggle,
+    guild_config,
     history,
     history_window_config,
+    message_archive,
 );